@@ -1,6 +1,14 @@
-use std::process;
+use std::{path::PathBuf, process};
 
-use clap::{Parser, arg};
+use clap::Parser;
+
+use crate::{
+    file_fill::MARKER_SPACING,
+    history,
+    pattern::{ByteOrder, GeneratorKind, PatternHint},
+    state::read_state_path,
+    utils::{SECTOR_SIZE, new_run_seed},
+};
 
 #[derive(Parser, Debug, Clone)]
 pub struct CommonArgs {
@@ -28,6 +36,68 @@ pub struct CommonArgs {
         help = "Show progress if NUM is not zero"
     )]
     pub show_progress: bool,
+    #[arg(
+        long = "seed",
+        value_name = "NUM",
+        default_value_t = 0,
+        help = "Run seed mixed into the pattern (0 = unseeded)"
+    )]
+    pub seed: u64,
+    #[arg(
+        long = "pattern",
+        value_enum,
+        value_name = "GENERATOR",
+        help = "Pattern generator (default: lcg when writing, auto-detect when reading)"
+    )]
+    pub pattern: Option<GeneratorKind>,
+    #[arg(
+        long = "sector-size",
+        value_name = "BYTES",
+        help = "Sector size, a power of two from 512 to 1 MiB (default: 512, or from the manifest when reading)"
+    )]
+    pub sector_size: Option<usize>,
+    #[arg(
+        long = "tolerance",
+        value_name = "WORDS",
+        help = "Words a sector may differ by and still count as changed (default: 2, or from the manifest when reading)"
+    )]
+    pub tolerance: Option<usize>,
+    #[arg(
+        long = "report",
+        value_name = "FILE",
+        help = "Write a JSON summary of the run to FILE"
+    )]
+    pub report: Option<String>,
+    #[arg(
+        long = "tui",
+        help = "Show a full-screen dashboard (keys: p pause, r resume, q abort)"
+    )]
+    pub tui: bool,
+    #[arg(
+        long = "speed-chart",
+        value_name = "PREFIX",
+        help = "Export speed over capacity to PREFIX.csv and PREFIX.svg"
+    )]
+    pub speed_chart: Option<String>,
+    #[arg(
+        long = "html",
+        value_name = "FILE",
+        help = "Write a self-contained HTML report of the run to FILE"
+    )]
+    pub html: Option<String>,
+    #[arg(
+        long = "history",
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true,
+        help = "Record the run in the history (default FILE: ~/.local/share/nf3/history.jsonl)"
+    )]
+    pub history: Option<Option<String>>,
+    #[arg(
+        long = "wait-lock",
+        help = "If another run uses PATH, wait for it to finish instead of refusing"
+    )]
+    pub wait_lock: bool,
     #[arg(
         value_name = "PATH",
         default_value = "",
@@ -37,6 +107,13 @@ pub struct CommonArgs {
 }
 
 impl CommonArgs {
+    /// Where `--history` records the run, `None` without it.
+    pub fn history_file(&self) -> Option<PathBuf> {
+        self.history
+            .as_ref()
+            .map(|f| f.as_ref().map_or_else(history::default_path, PathBuf::from))
+    }
+
     pub fn validate_args(&mut self) {
         if self.dev_path.is_empty() {
             eprintln!("Error: Device path must be specified.");
@@ -50,6 +127,37 @@ impl CommonArgs {
             eprintln!("Error: End at must be greater than or equal to start at, or zero");
             process::exit(1);
         }
+        if let Some(size) = self.sector_size
+            && !(size.is_power_of_two()
+                && (SECTOR_SIZE as u64..=MARKER_SPACING).contains(&(size as u64)))
+        {
+            eprintln!("Error: Sector size must be a power of two from 512 to 1048576");
+            process::exit(1);
+        }
+        if let Some(tolerance) = self.tolerance
+            && tolerance >= self.sector_size.unwrap_or(SECTOR_SIZE) / 8
+        {
+            eprintln!("Error: Tolerance must be less than the number of words in a sector");
+            process::exit(1);
+        }
+
+        // `adjust_dev_path` changes into PATH, output files given relative to
+        // the current directory must not end up on the drive under test.
+        for file in [&mut self.report, &mut self.speed_chart, &mut self.html] {
+            make_absolute(file);
+        }
+        if let Some(file) = &mut self.history {
+            make_absolute(file);
+        }
+    }
+}
+
+/// Resolve a relative output path against the current directory.
+fn make_absolute(file: &mut Option<String>) {
+    if let Some(f) = file
+        && let Ok(abs) = std::path::absolute(&*f)
+    {
+        *f = abs.to_string_lossy().into_owned();
     }
 }
 
@@ -71,6 +179,41 @@ pub struct WriteArgs {
         default_value_t = 0
     )]
     pub max_write_rate: i64,
+
+    /// Probe the device write-back cache up to MiB before writing (0 = skip)
+    #[arg(long = "probe-cache", value_name = "MiB", default_value_t = 0)]
+    pub probe_cache: u64,
+
+    /// Re-read NUM random sectors of the written files after each file and
+    /// stop as soon as earlier data was overwritten (0 = no spot checks)
+    #[arg(long = "spot-check", value_name = "NUM", default_value_t = 0)]
+    pub spot_check: usize,
+
+    /// Continue an interrupted run from its state file in PATH
+    #[arg(long = "resume", default_value_t = false)]
+    pub resume: bool,
+
+    /// Allocate the files but write only one marker sector per MiB
+    #[arg(long = "quick", default_value_t = false)]
+    pub quick: bool,
+
+    /// Verify the files right after writing them and print a combined report
+    #[arg(long = "verify", default_value_t = false)]
+    pub verify: bool,
+
+    /// Write even to the root filesystem, a fixed or network disk, or a
+    /// directory full of other files
+    #[arg(long = "force", default_value_t = false)]
+    pub force: bool,
+
+    /// Print which files would be deleted and created, and how much would be
+    /// written, without touching the disk
+    #[arg(long = "dry-run", default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Pick a fresh run seed instead of `--seed`
+    #[arg(long = "random-seed", default_value_t = false)]
+    pub random_seed: bool,
 }
 
 impl WriteArgs {
@@ -80,6 +223,17 @@ impl WriteArgs {
             eprintln!("Error: Max write rate must be non-negative");
             process::exit(1);
         }
+        if self.dry_run && self.resume {
+            eprintln!("Error: --dry-run and --resume are mutually exclusive");
+            process::exit(1);
+        }
+        if self.random_seed {
+            if self.common.seed != 0 {
+                eprintln!("Error: --seed and --random-seed are mutually exclusive");
+                process::exit(1);
+            }
+            self.common.seed = new_run_seed();
+        }
         // don't sure about it
         #[cfg(unix)]
         {
@@ -115,18 +269,69 @@ pub struct ReadArgs {
     )]
     pub max_read_rate: i64,
 
+    /// Byte order of the files (default: detected per file)
+    #[arg(long = "byte-order", value_enum, value_name = "ORDER")]
+    pub byte_order: Option<ByteOrder>,
+
+    /// Continue an interrupted verification from its state file
+    #[arg(long = "resume", default_value_t = false)]
+    pub resume: bool,
+
+    /// Keep the resume state in FILE (default: in ~/.local/share/nf3, named after PATH)
+    #[arg(long = "state", value_name = "FILE")]
+    pub state: Option<String>,
+
+    /// Check only the marker sectors of a quick run (default: from the manifest)
+    #[arg(long = "quick", default_value_t = false)]
+    pub quick: bool,
+
+    /// Check only NUM random sectors per file and extrapolate (0 = read everything)
+    #[arg(long = "sample", value_name = "NUM", default_value_t = 0)]
+    pub sample: usize,
+
+    /// Seed choosing the sampled sectors, the same seed samples the same sectors
+    #[arg(long = "sample-seed", value_name = "NUM", default_value_t = 1)]
+    pub sample_seed: u64,
+
+    /// Write the results as JUnit XML to PATH, one test case per file
+    #[arg(long = "junit", value_name = "PATH")]
+    pub junit: Option<String>,
+
     /// Should program read a single file
     #[arg(short = 'S', long = "read-single-file", default_value_t = false)]
     pub read_single_file: bool,
 }
 
 impl ReadArgs {
+    /// `--state`, or the default state file of PATH, see `read_state_path`.
+    pub fn state_file(&self) -> PathBuf {
+        self.state
+            .as_ref()
+            .map_or_else(|| read_state_path(&self.common.dev_path), PathBuf::from)
+    }
+
+    pub fn pattern_hint(&self) -> PatternHint {
+        PatternHint {
+            kind: self.common.pattern,
+            seed: self.common.seed,
+            order: self.byte_order,
+            sector_size: self.common.sector_size,
+            tolerance: self.common.tolerance,
+        }
+    }
+
     pub fn validate_args(&mut self) {
         self.common.validate_args();
         if self.max_read_rate < 0 {
             eprintln!("Error: Max read rate must be non-negative");
             process::exit(1);
         }
+        if self.sample > 0 && self.resume {
+            eprintln!("Error: --sample and --resume are mutually exclusive");
+            process::exit(1);
+        }
+        make_absolute(&mut self.junit);
+        make_absolute(&mut self.state);
         if self.common.dev_path.ends_with(".h2w") {
             self.read_single_file = true;
             return;
@@ -0,0 +1,96 @@
+// f3core/src/dashboard.rs
+
+//! Events of a run for the full-screen dashboard (`--tui`).
+//!
+//! The engine calls `emit` at the interesting points; without a running
+//! dashboard this costs one atomic load. The terminal side lives in
+//! `crate::tui`, behind the `tui` feature.
+
+use std::{
+    io,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+};
+
+use crate::verify::FileStats;
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A write or read pass over `files` begins.
+    Start {
+        tool: &'static str,
+        path: String,
+        total_bytes: u64,
+        files: Vec<i64>,
+    },
+    FileStarted(i64),
+    /// A `Flow` measurement; `speed` is the instantaneous speed in bytes/s.
+    Progress {
+        processed: u64,
+        total: u64,
+        speed: f64,
+    },
+    /// `stats` is `None` for written files.
+    FileDone {
+        number: i64,
+        stats: Option<FileStats>,
+    },
+    /// The run is over; the dashboard waits for a key before closing.
+    Finished,
+}
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static SINK: Mutex<Option<Sender<Event>>> = Mutex::new(None);
+
+/// True while a dashboard is shown, so the progress line is not printed.
+#[inline]
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+pub fn emit(event: Event) {
+    if !active() {
+        return;
+    }
+    if let Some(tx) = SINK.lock().unwrap().as_ref() {
+        let _ = tx.send(event);
+    }
+}
+
+#[cfg_attr(not(all(feature = "tui", unix)), allow(dead_code))]
+pub(crate) fn attach(tx: Sender<Event>) {
+    *SINK.lock().unwrap() = Some(tx);
+    ACTIVE.store(true, Ordering::SeqCst);
+}
+
+#[cfg_attr(not(all(feature = "tui", unix)), allow(dead_code))]
+pub(crate) fn detach() {
+    ACTIVE.store(false, Ordering::SeqCst);
+    SINK.lock().unwrap().take();
+}
+
+/// Take over the terminal. Everything printed meanwhile is kept and shown
+/// once `stop` is called.
+pub fn start() -> io::Result<()> {
+    #[cfg(all(feature = "tui", unix))]
+    {
+        crate::tui::start()
+    }
+    #[cfg(not(all(feature = "tui", unix)))]
+    {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the dashboard needs a Unix build with the `tui` feature",
+        ))
+    }
+}
+
+/// Close the dashboard (after a key press, unless the run was aborted) and
+/// give the terminal back. Does nothing if no dashboard was started.
+pub fn stop() {
+    #[cfg(all(feature = "tui", unix))]
+    crate::tui::stop();
+}
@@ -0,0 +1,232 @@
+// f3core/src/device.rs
+
+//! What is being tested: the mount holding the target path and its backing
+//! block device, read from `/proc/self/mountinfo` and `/sys/block`.
+//!
+//! Everything is looked up below a root directory (`/` normally), so tests
+//! can use a fake tree.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Size unit of `/sys/block/<dev>/size`, whatever the logical block size.
+const SYSFS_SECTOR: u64 = 512;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub mount_point: String,
+    pub fs_type: String,
+    /// Mount source, e.g. `/dev/sdb1` or `server:/export`.
+    pub source: String,
+    /// Whole-disk name in `/sys/block`, e.g. `sdb`.
+    pub block_device: Option<String>,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    /// Size the device advertises, in bytes.
+    pub size: Option<u64>,
+    pub removable: Option<bool>,
+    /// `usb`, `nvme`, `mmc`, `ata`, `virtio`, ...
+    pub bus: Option<String>,
+}
+
+/// One line of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountEntry {
+    /// `major:minor` of the mounted device.
+    pub dev: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub source: String,
+}
+
+impl DeviceInfo {
+    /// Identify the device holding `path`, `None` if its mount is unknown
+    /// (e.g. not Linux).
+    pub fn probe(path: &str) -> Option<DeviceInfo> {
+        Self::probe_in(Path::new("/"), path)
+    }
+
+    /// Like `probe`, with `/proc` and `/sys` taken from below `root`.
+    pub fn probe_in(root: &Path, path: &str) -> Option<DeviceInfo> {
+        let mounts = parse_mountinfo(&fs::read_to_string(root.join("proc/self/mountinfo")).ok()?);
+        let path = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+        let mount = find_mount(&mounts, &path)?;
+
+        let mut info = DeviceInfo {
+            mount_point: mount.mount_point.clone(),
+            fs_type: mount.fs_type.clone(),
+            source: mount.source.clone(),
+            ..Default::default()
+        };
+        let sys = root.join("sys");
+        let sys = fs::canonicalize(&sys).unwrap_or(sys);
+        let Some(disk) = block_name(&sys, mount).and_then(|name| whole_disk(&sys, &name)) else {
+            return Some(info);
+        };
+
+        let disk_dir = sys.join("block").join(&disk);
+        let device_dir = disk_dir.join("device");
+        info.size = read_attr(&disk_dir, "size")
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(|sectors| sectors * SYSFS_SECTOR);
+        info.removable = read_attr(&disk_dir, "removable").map(|r| r == "1");
+        info.vendor = read_attr(&device_dir, "vendor");
+        info.model = read_attr(&device_dir, "model");
+        // USB sticks keep the serial on the USB device, a few levels up.
+        let real = fs::canonicalize(&device_dir).unwrap_or(device_dir);
+        info.serial = read_attr(&real, "serial").or_else(|| {
+            real.ancestors()
+                .take_while(|dir| dir.starts_with(&sys))
+                .find(|dir| dir.join("idVendor").exists())
+                .and_then(|usb| read_attr(usb, "serial"))
+        });
+        info.bus = bus_of(&real, &disk);
+        info.block_device = Some(disk);
+        Some(info)
+    }
+
+    /// Stable name of the drive for the history: vendor, model and serial
+    /// when the serial is known.
+    pub fn identity(&self) -> Option<String> {
+        let serial = self.serial.as_ref()?;
+        let parts: Vec<&str> = [self.vendor.as_deref(), self.model.as_deref(), Some(serial)]
+            .into_iter()
+            .flatten()
+            .collect();
+        Some(parts.join(" "))
+    }
+
+    /// One line for reports, e.g. `Kingston DataTraveler 3.0 (sdb, usb, removable)`.
+    pub fn describe(&self) -> String {
+        let name: Vec<&str> = [self.vendor.as_deref(), self.model.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        let mut details: Vec<String> = Vec::new();
+        details.extend(self.block_device.clone());
+        details.extend(self.bus.clone());
+        match self.removable {
+            Some(true) => details.push("removable".into()),
+            Some(false) => details.push("fixed".into()),
+            None => {}
+        }
+        let name = if name.is_empty() {
+            self.source.clone()
+        } else {
+            name.join(" ")
+        };
+        if details.is_empty() {
+            name
+        } else {
+            format!("{} ({})", name, details.join(", "))
+        }
+    }
+}
+
+pub fn parse_mountinfo(text: &str) -> Vec<MountEntry> {
+    text.lines()
+        .filter_map(|line| {
+            let (left, right) = line.split_once(" - ")?;
+            let left: Vec<&str> = left.split(' ').collect();
+            let mut right = right.split(' ');
+            Some(MountEntry {
+                dev: left.get(2)?.to_string(),
+                mount_point: unescape(left.get(4)?),
+                fs_type: right.next()?.to_string(),
+                source: unescape(right.next()?),
+            })
+        })
+        .collect()
+}
+
+/// The mount `path` is on: the longest mount point containing it (the last
+/// one mounted wins for stacked mounts).
+pub fn find_mount<'a>(mounts: &'a [MountEntry], path: &Path) -> Option<&'a MountEntry> {
+    mounts
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| path.starts_with(&m.mount_point))
+        .max_by_key(|(i, m)| (m.mount_point.len(), *i))
+        .map(|(_, m)| m)
+}
+
+/// mountinfo escapes blanks and backslashes as `\ooo`.
+fn unescape(field: &str) -> String {
+    let mut out = Vec::with_capacity(field.len());
+    let bytes = field.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && let Some(code) = field
+                .get(i + 1..i + 4)
+                .and_then(|o| u8::from_str_radix(o, 8).ok())
+        {
+            out.push(code);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Kernel name of the mounted block device (`sdb1`), by its number or source.
+fn block_name(sys: &Path, mount: &MountEntry) -> Option<String> {
+    let by_dev = fs::read_link(sys.join("dev/block").join(&mount.dev))
+        .ok()
+        .and_then(|link| Some(link.file_name()?.to_string_lossy().into_owned()));
+    by_dev.or_else(|| {
+        let source =
+            fs::canonicalize(&mount.source).unwrap_or_else(|_| mount.source.clone().into());
+        source
+            .strip_prefix("/dev")
+            .ok()
+            .and_then(|p| Some(p.file_name()?.to_string_lossy().into_owned()))
+    })
+}
+
+/// The `/sys/block` entry of `name`, itself or the disk it is a partition of.
+fn whole_disk(sys: &Path, name: &str) -> Option<String> {
+    let block = sys.join("block");
+    if block.join(name).exists() {
+        return Some(name.to_string());
+    }
+    fs::read_dir(&block)
+        .ok()?
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .find(|disk| block.join(disk).join(name).exists())
+}
+
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    let value = fs::read_to_string(dir.join(name)).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn bus_of(device: &Path, disk: &str) -> Option<String> {
+    let path = device.to_string_lossy();
+    let bus = [
+        ("/usb", "usb"),
+        ("/nvme", "nvme"),
+        ("/mmc", "mmc"),
+        ("/ata", "ata"),
+        ("/virtio", "virtio"),
+    ]
+    .into_iter()
+    .find(|(part, _)| path.contains(part))
+    .map(|(_, bus)| bus)
+    .or_else(|| {
+        [("nvme", "nvme"), ("mmcblk", "mmc"), ("vd", "virtio")]
+            .into_iter()
+            .find(|(prefix, _)| disk.starts_with(prefix))
+            .map(|(_, bus)| bus)
+    })?;
+    Some(bus.to_string())
+}
@@ -2,33 +2,39 @@
 
 use std::{
     fs::File,
-    io::{self, ErrorKind, Write},
+    io::{self, ErrorKind, Seek, SeekFrom, Write},
 };
 
 use crate::{
     flow::{DynamicBuffer, Flow},
-    utils::{GIB, SECTOR_SIZE, random_number},
+    interrupt::interrupted,
+    pattern::Pattern,
+    utils::{GIB, allocate},
 };
 
-/// Fill the provided buffer slice in SECTOR_SIZE chunks with a deterministic
-/// pseudorandom sequence starting from `offset`.
+/// Distance between the marker sectors of a quick (`--quick`) run.
+pub const MARKER_SPACING: u64 = 1024 * 1024; // 1 MiB
+
+/// Fill the provided buffer slice in sectors of `pattern` with the deterministic
+/// pseudorandom data of `pattern`, starting from `offset`.
+///
+/// Every sector starts with its offset. A non-zero seed is stored in the
+/// second word and mixed into the data, so data written by another run
+/// can be told apart from data of this run.
 ///
-/// Returns the new offset = original offset + number_of_sectors * SECTOR_SIZE.
-pub fn fill_buffer(buf: &mut [u8], size: usize, offset: u64) -> u64 {
+/// Returns the new offset = original offset + number_of_sectors * sector size.
+pub fn fill_buffer(buf: &mut [u8], size: usize, offset: u64, pattern: &Pattern) -> u64 {
     let mut current = offset;
+    let sector_size = pattern.sector_size;
     assert!(size > 0, "buffer must not be empty");
     assert!(
-        size % SECTOR_SIZE == 0,
-        "buffer size must be multiple of SECTOR_SIZE"
+        size.is_multiple_of(sector_size),
+        "buffer size must be multiple of the sector size"
     );
 
-    for chunk in buf.chunks_exact_mut(SECTOR_SIZE) {
-        let mut prev = current;
-        for word in chunk.chunks_exact_mut(8) {
-            word.copy_from_slice(&prev.to_ne_bytes());
-            prev = random_number(prev);
-        }
-        current = current.wrapping_add(SECTOR_SIZE as u64);
+    for chunk in buf[..size].chunks_exact_mut(sector_size) {
+        pattern.fill_sector(chunk, current);
+        current = current.wrapping_add(sector_size as u64);
     }
 
     current
@@ -43,6 +49,7 @@ pub fn write_chunk(
     file: &mut impl Write,
     mut remaining: usize,
     poffset: &mut u64,
+    pattern: &Pattern,
 ) -> io::Result<()> {
     // Ensure our slice is at least `*remaining` long
     let buf = dbuf.get_buf(remaining);
@@ -50,7 +57,7 @@ pub fn write_chunk(
 
     while remaining > 0 {
         let turn: usize = buf_len.min(remaining);
-        *poffset = fill_buffer(&mut buf[..turn], turn, *poffset);
+        *poffset = fill_buffer(&mut buf[..turn], turn, *poffset, pattern);
         file.write_all(&buf[..turn])?;
         remaining -= turn;
     }
@@ -58,9 +65,15 @@ pub fn write_chunk(
     Ok(())
 }
 
-/// Internal: for a newly opened file, write out `size` bytes in SECTOR_SIZE chunks.
+/// Internal: for a newly opened file, write out `size` bytes in sector sized chunks.
 /// Each chunk is generated by `fill_buffer`. No return value.
-pub fn fill_file(file: &mut File, number: i64, size: u64, fw: &mut Flow) -> io::Result<()> {
+pub fn fill_file(
+    file: &mut File,
+    number: i64,
+    size: u64,
+    pattern: &Pattern,
+    fw: &mut Flow,
+) -> io::Result<()> {
     let mut dbuf = DynamicBuffer::new();
     let mut offset = number as u64 * GIB;
     let mut remaining = size;
@@ -72,7 +85,7 @@ pub fn fill_file(file: &mut File, number: i64, size: u64, fw: &mut Flow) -> io::
         if chunk_size > remaining {
             chunk_size = remaining;
         }
-        if let Err(e) = write_chunk(&mut dbuf, file, chunk_size as usize, &mut offset) {
+        if let Err(e) = write_chunk(&mut dbuf, file, chunk_size as usize, &mut offset, pattern) {
             if e.raw_os_error() == Some(28) {
                 // ENOSPC
                 // println!("No space left while writing file: {}.h2w", number);
@@ -80,7 +93,7 @@ pub fn fill_file(file: &mut File, number: i64, size: u64, fw: &mut Flow) -> io::
             }
             // Other error
             eprintln!("Error writing chunk: {}", e);
-            break;
+            return Err(e);
         }
         remaining -= chunk_size;
 
@@ -91,8 +104,44 @@ pub fn fill_file(file: &mut File, number: i64, size: u64, fw: &mut Flow) -> io::
             eprintln!("Error during measurement: {}", e);
             return Err(e);
         }
+
+        if interrupted() {
+            // end_measurement syncs what was written so far
+            fw.end_measurement(file)?;
+            return Err(io::Error::from(ErrorKind::Interrupted));
+        }
     }
     fw.end_measurement(file)?;
 
     Ok(())
 }
+
+/// Quick mode: allocate `size` bytes for `file` but write pattern data only
+/// into the first sector of every MARKER_SPACING bytes. A drive that maps
+/// several blocks onto the same memory still overwrites earlier markers.
+pub fn fill_markers(
+    file: &mut File,
+    number: i64,
+    size: u64,
+    pattern: &Pattern,
+    fw: &mut Flow,
+) -> io::Result<()> {
+    allocate(file, size)?;
+
+    let base = number as u64 * GIB;
+    let mut sector = vec![0u8; pattern.sector_size];
+    for offset in (0..size).step_by(MARKER_SPACING as usize) {
+        pattern.fill_sector(&mut sector, base + offset);
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&sector)?;
+
+        if interrupted() {
+            file.sync_data()?;
+            return Err(io::Error::from(ErrorKind::Interrupted));
+        }
+    }
+    file.sync_data()?;
+    fw.skip(size);
+
+    Ok(())
+}
@@ -13,10 +13,25 @@ use crossterm::{
     style::Print,
     terminal::{Clear, ClearType},
 };
+use serde::{Deserialize, Serialize};
 // use simple_log::new;
 
 // Internal crate imports
-use crate::utils::{adjust_unit, pr_time_str};
+use crate::{
+    dashboard::{self, Event},
+    interrupt::wait_while_paused,
+    speed::{SpeedLog, SpeedSample},
+    utils::{adjust_unit, pr_time_str},
+};
+
+/// Counters of a `Flow` that survive an interrupted run, see `Flow::restore`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowState {
+    pub total_size: u64,
+    pub total_processed: u64,
+    pub measured_blocks: u64,
+    pub measured_time_ms: u64,
+}
 
 enum State {
     Inc,
@@ -58,6 +73,8 @@ pub struct Flow {
     last_inst_bps: f64,
 
     last_report_time: Instant,
+
+    speeds: SpeedLog,
 }
 
 impl Flow {
@@ -87,15 +104,70 @@ impl Flow {
             bpd_high: 0,
             last_inst_bps: 0.0,
             last_report_time: Instant::now(),
+            speeds: SpeedLog::default(),
         }
     }
 
+    /// Process in multiples of `block_size` bytes, the sector size of the run.
+    pub fn with_block_size(mut self, block_size: usize) -> Flow {
+        self.block_size = block_size as i32;
+        self
+    }
+
     pub fn show_progress(&self) -> bool {
         self.progress
     }
 
+    /// The progress line, unless the dashboard shows it.
+    fn prints_progress(&self) -> bool {
+        self.progress && !dashboard::active()
+    }
+
+    pub fn state(&self) -> FlowState {
+        FlowState {
+            total_size: self.total_size,
+            total_processed: self.total_processed,
+            measured_blocks: self.measured_blocks,
+            measured_time_ms: self.measured_time_ms,
+        }
+    }
+
+    /// Continue the totals and speed average of an interrupted run.
+    pub fn restore(&mut self, state: &FlowState) {
+        self.total_size = state.total_size;
+        self.total_processed = state.total_processed;
+        self.measured_blocks = state.measured_blocks;
+        self.measured_time_ms = state.measured_time_ms;
+    }
+
+    /// Instantaneous speeds of the measurements over the bytes processed.
+    pub fn speed_samples(&self) -> &[SpeedSample] {
+        self.speeds.samples()
+    }
+
+    pub fn total_processed(&self) -> u64 {
+        self.total_processed
+    }
+
+    /// Average speed in bytes per second, falling back to `bytes / total_time_ms`
+    /// when there are not enough measurements. 0 if nothing is known.
+    pub fn avg_speed_or(&self, total_time_ms: u64) -> f64 {
+        if self.has_enough_measurements() {
+            self.get_avg_speed()
+        } else if total_time_ms > 0 {
+            self.total_processed as f64 * 1000.0 / total_time_ms as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Account for `bytes` that were processed without being measured.
+    pub fn skip(&mut self, bytes: u64) {
+        self.total_processed += bytes;
+    }
+
     pub fn start_measurement(&mut self) {
-        if self.progress && self.has_enough_measurements() {
+        if self.prints_progress() && self.has_enough_measurements() {
             self.report_progress();
         }
 
@@ -179,6 +251,8 @@ impl Flow {
 
         assert!(self.processed_blocks == self.blocks_per_delay);
         self.flush_chunk(file)?;
+        // A pause is not part of the measurement.
+        self.measure_start_time += wait_while_paused();
         let end_time = Instant::now();
         let mut delay: u64 = (end_time - self.measure_start_time
             + Duration::from_micros(self.acc_delay_us))
@@ -222,9 +296,17 @@ impl Flow {
         self.last_inst_bps = inst_speed;
 
         self.adjust_state(inst_speed, delay);
+        self.speeds.push(self.total_processed, inst_speed);
+
+        dashboard::emit(Event::Progress {
+            processed: self.total_processed,
+            total: self.total_size.max(self.total_processed),
+            speed: inst_speed,
+        });
 
         // Using single thread for reports is more effective accoording to tests
-        if self.progress && self.last_report_time.elapsed().as_secs() >= Self::REPORT_INTERVAL_SECS
+        if self.prints_progress()
+            && self.last_report_time.elapsed().as_secs() >= Self::REPORT_INTERVAL_SECS
         {
             self.report_progress();
         }
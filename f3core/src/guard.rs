@@ -0,0 +1,96 @@
+// f3core/src/guard.rs
+
+//! Pre-flight check of nf3_write: filling `/` or a home directory by a typo
+//! is worse than a refused run, so suspicious targets need `--force`.
+
+use std::{fmt, fs};
+
+use crate::{device::DeviceInfo, lock::LOCK_NAME};
+
+/// More entries than this that nf3 didn't create make PATH look like a
+/// directory in use rather than a drive prepared for testing.
+pub const MAX_FOREIGN_FILES: usize = 16;
+
+const NETWORK_FS: [&str; 12] = [
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "ncpfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hazard {
+    RootFs,
+    /// Not removable and not on USB: most likely an internal disk.
+    FixedDisk(String),
+    NetworkFs(String),
+    ForeignFiles(usize),
+    /// The mount or device of PATH could not be identified (no sysfs,
+    /// another OS): nothing above can be ruled out.
+    UnknownDevice,
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hazard::RootFs => write!(f, "it is on the root filesystem"),
+            Hazard::FixedDisk(dev) => write!(f, "{} is not a removable device", dev),
+            Hazard::NetworkFs(fs_type) => write!(f, "it is on a network filesystem ({})", fs_type),
+            Hazard::ForeignFiles(n) => write!(f, "it contains {} files not created by nf3", n),
+            Hazard::UnknownDevice => write!(f, "its device could not be identified"),
+        }
+    }
+}
+
+/// What makes `path` (on `device`, if known) a dangerous target.
+pub fn check(path: &str, device: Option<&DeviceInfo>) -> Vec<Hazard> {
+    hazards(device, count_foreign_files(path))
+}
+
+pub fn hazards(device: Option<&DeviceInfo>, foreign_files: usize) -> Vec<Hazard> {
+    let mut found = Vec::new();
+    match device {
+        None => found.push(Hazard::UnknownDevice),
+        Some(dev) => {
+            if dev.mount_point == "/" {
+                found.push(Hazard::RootFs);
+            }
+            if dev.removable == Some(false) && dev.bus.as_deref() != Some("usb") {
+                found.push(Hazard::FixedDisk(dev.describe()));
+            }
+            if NETWORK_FS.contains(&dev.fs_type.as_str()) {
+                found.push(Hazard::NetworkFs(dev.fs_type.clone()));
+            }
+        }
+    }
+    if foreign_files > MAX_FOREIGN_FILES {
+        found.push(Hazard::ForeignFiles(foreign_files));
+    }
+    found
+}
+
+/// Entries of `path` other than `<N>.h2w`, the `nf3-*` sidecar files and
+/// the lock file.
+pub fn count_foreign_files(path: &str) -> usize {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let h2w = name
+                .strip_suffix(".h2w")
+                .is_some_and(|n| n.parse::<i64>().is_ok());
+            !h2w && !name.starts_with("nf3-") && name != LOCK_NAME
+        })
+        .count()
+}
@@ -0,0 +1,180 @@
+// f3core/src/history.rs
+
+//! Append-only JSON-lines log of runs (`--history`), listed per device by
+//! `nf3 history` to track drives tested again and again.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Result, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    report::{RunReport, Verdict},
+    utils::{adjust_unit, data_dir},
+};
+
+pub const HISTORY_NAME: &str = "history.jsonl";
+
+/// One run of nf3_write or nf3_read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix time (seconds) the run started at.
+    pub started_at: u64,
+    pub tool: String,
+    /// What the runs of a drive are grouped by: vendor, model and serial,
+    /// or the path when the serial is unknown.
+    pub device: String,
+    pub path: String,
+    pub host: String,
+    pub run_id: Option<String>,
+    pub sector_size: usize,
+    pub quick: bool,
+    pub sampled: bool,
+    /// Bytes per second.
+    pub write_speed: Option<f64>,
+    pub read_speed: Option<f64>,
+    pub bytes_written: Option<u64>,
+    pub bytes_ok: Option<u64>,
+    pub bytes_lost: Option<u64>,
+    pub missing_files: usize,
+    pub verdict: Verdict,
+}
+
+impl HistoryEntry {
+    pub fn new(tool: &str, report: &RunReport) -> Self {
+        let write = report.write.as_ref();
+        let read = report.read.as_ref();
+        let quick = write.is_some_and(|w| w.quick) || read.is_some_and(|r| r.quick);
+        // Quick runs hardly write or read anything.
+        let speed = |bps: f64| (bps > 0.0 && !quick).then_some(bps);
+        HistoryEntry {
+            started_at: report.started_at,
+            tool: tool.to_string(),
+            device: report
+                .device
+                .as_ref()
+                .and_then(|d| d.identity())
+                .unwrap_or_else(|| report.path.clone()),
+            path: report.path.clone(),
+            host: report.host.clone(),
+            run_id: report.run_id.clone(),
+            sector_size: read
+                .map(|r| r.sector_size)
+                .or(write.map(|w| w.sector_size))
+                .unwrap_or_default(),
+            quick,
+            sampled: read.is_some_and(|r| r.estimate.is_some()),
+            write_speed: write.and_then(|w| speed(w.avg_speed)),
+            read_speed: read.and_then(|r| speed(r.avg_speed)),
+            bytes_written: write.map(|w| w.bytes_written),
+            bytes_ok: read.map(|r| r.scaled_bytes(r.totals.secs_ok())),
+            bytes_lost: read.map(|r| r.scaled_bytes(r.totals.secs_lost())),
+            missing_files: read.map_or(0, |r| r.missing.len()),
+            verdict: report.verdict(),
+        }
+    }
+}
+
+/// `history.jsonl` in `utils::data_dir`.
+pub fn default_path() -> PathBuf {
+    data_dir().join(HISTORY_NAME)
+}
+
+pub fn append(file: &Path, entry: &HistoryEntry) -> Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let line = serde_json::to_string(entry).map_err(io::Error::other)?;
+    let mut f = OpenOptions::new().create(true).append(true).open(file)?;
+    writeln!(f, "{}", line)
+}
+
+/// All entries of `file`, oldest first; lines that don't parse are skipped.
+pub fn load(file: &Path) -> Result<Vec<HistoryEntry>> {
+    let f = match fs::File::open(file) {
+        Ok(f) => f,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for (i, line) in BufReader::new(f).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!(
+                "Warning: {}:{}: skipping unreadable entry: {}",
+                file.display(),
+                i + 1,
+                e
+            ),
+        }
+    }
+    entries.sort_by_key(|e: &HistoryEntry| e.started_at);
+    Ok(entries)
+}
+
+pub fn by_device(entries: &[HistoryEntry]) -> BTreeMap<&str, Vec<&HistoryEntry>> {
+    let mut devices: BTreeMap<&str, Vec<&HistoryEntry>> = BTreeMap::new();
+    for e in entries {
+        devices.entry(&e.device).or_default().push(e);
+    }
+    devices
+}
+
+/// How speeds and data loss moved from the first to the last run of a device.
+pub fn trend(runs: &[&HistoryEntry]) -> Vec<String> {
+    let mut trend = Vec::new();
+    let change = |values: Vec<f64>| match (values.first(), values.last()) {
+        (Some(first), Some(last)) if values.len() > 1 && *first > 0.0 => {
+            Some((last - first) / first * 100.0)
+        }
+        _ => None,
+    };
+    if let Some(p) = change(runs.iter().filter_map(|r| r.write_speed).collect()) {
+        trend.push(format!("write speed {:+.1}%", p));
+    }
+    if let Some(p) = change(runs.iter().filter_map(|r| r.read_speed).collect()) {
+        trend.push(format!("read speed {:+.1}%", p));
+    }
+    let lost: Vec<u64> = runs.iter().filter_map(|r| r.bytes_lost).collect();
+    if let (Some(first), Some(last)) = (lost.first(), lost.last())
+        && lost.len() > 1
+        && first != last
+    {
+        let (f, fu) = adjust_unit(*first as f64);
+        let (l, lu) = adjust_unit(*last as f64);
+        trend.push(format!("lost data {:.2} {} -> {:.2} {}", f, fu, l, lu));
+    }
+    trend
+}
+
+/// `YYYY-MM-DD HH:MM` (UTC) of a Unix time.
+pub fn utc_time(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    // Civil date from days since 1970-01-01 (H. Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60
+    )
+}
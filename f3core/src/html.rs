@@ -0,0 +1,277 @@
+// f3core/src/html.rs
+
+//! Single-file HTML version of a `RunReport`, for sharing results (supplier
+//! complaints, refund claims) with people who won't read JSON.
+
+use std::fmt::Write as _;
+
+use crate::{
+    history::utc_time,
+    report::{ReadSummary, RunReport, Verdict},
+    speed,
+    utils::adjust_unit,
+    verify::FileStats,
+};
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:960px;margin:2em auto;color:#222}\
+table{border-collapse:collapse;margin:1em 0}td,th{border:1px solid #ccc;padding:4px 8px;text-align:right}\
+th{background:#f3f3f3}td:first-child,th:first-child{text-align:left}\
+.verdict{font-size:1.4em;font-weight:bold;padding:.6em 1em;border-radius:4px;color:white}\
+.ok{background:#2b8a3e}.failed{background:#c92a2a}.partial{background:#e67700}\
+.map{display:flex;flex-wrap:wrap;gap:2px;margin:1em 0}.map span{width:14px;height:14px;display:inline-block}\
+.legend span{display:inline-block;margin-right:1em}.legend i{display:inline-block;width:12px;height:12px;margin-right:4px}";
+
+/// Colours of the sector classes, shared by the map and its legend.
+const CLASSES: [(&str, &str); 7] = [
+    ("ok", "#2b8a3e"),
+    ("corrupted", "#c92a2a"),
+    ("changed", "#f59f00"),
+    ("overwritten", "#ae3ec9"),
+    ("stale", "#1971c2"),
+    ("erased", "#868e96"),
+    ("missing", "#212529"),
+];
+
+pub fn render(report: &RunReport) -> String {
+    let mut html = String::new();
+    let verdict = report.verdict();
+    let class = match verdict {
+        Verdict::Ok => "ok",
+        Verdict::Failed | Verdict::FakeCapacity(_) => "failed",
+        Verdict::Incomplete | Verdict::NotVerified => "partial",
+    };
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>NeoF3 report - {}</title><style>{}</style></head><body>",
+        escape(&report.path),
+        STYLE
+    )
+    .unwrap();
+    writeln!(html, "<h1>NeoF3 report</h1>").unwrap();
+    writeln!(
+        html,
+        "<p class=\"verdict {}\">{}</p>",
+        class,
+        escape(&verdict.to_string())
+    )
+    .unwrap();
+
+    html.push_str("<h2>Device</h2><table>");
+    let mut row = |name: &str, value: String| {
+        write!(
+            html,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            name,
+            escape(&value)
+        )
+        .unwrap();
+    };
+    row("Path", report.path.clone());
+    row("Host", report.host.clone());
+    row("Run", report.run_id.clone().unwrap_or_else(|| "-".into()));
+    row("Started (UTC)", utc_time(report.started_at));
+    row("NeoF3 version", report.version.clone());
+    if let Some(dev) = &report.device {
+        row("Device", dev.describe());
+        row("Serial", dev.serial.clone().unwrap_or_else(|| "-".into()));
+        row("Advertised size", bytes_or_dash(dev.size));
+        row(
+            "Mount",
+            format!("{} ({}, {})", dev.mount_point, dev.fs_type, dev.source),
+        );
+    }
+    row("Filesystem size (claimed)", bytes_or_dash(report.fs_size));
+    row("Free at start", bytes_or_dash(report.fs_free));
+    if let Some(write) = &report.write {
+        row("Written", bytes(write.bytes_written));
+        if let Some(at) = write.fake_capacity_at {
+            row("Fake capacity detected at", bytes(at));
+        }
+        row("Writing speed", speed_str(write.avg_speed));
+    }
+    if let Some(read) = &report.read {
+        let ok = read.scaled_bytes(read.totals.secs_ok());
+        let lost = read.scaled_bytes(read.totals.secs_lost());
+        row("Verified OK", bytes(ok));
+        row("Lost", bytes(lost));
+        if let Some(e) = read.estimate {
+            row(
+                "Lost (95% interval)",
+                format!("{} - {}", bytes(e.lost_low), bytes(e.lost_high)),
+            );
+        }
+        if !read.quick {
+            row("Reading speed", speed_str(read.avg_speed));
+        }
+    }
+    html.push_str("</table>\n");
+
+    if let Some(read) = &report.read {
+        render_read(&mut html, read);
+    }
+
+    let write_speeds = report.write.as_ref().map_or(&[][..], |w| &w.speeds[..]);
+    let read_speeds = report.read.as_ref().map_or(&[][..], |r| &r.speeds[..]);
+    if !write_speeds.is_empty() || !read_speeds.is_empty() {
+        html.push_str("<h2>Speed</h2>\n");
+        html.push_str(&speed::to_svg(write_speeds, read_speeds));
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+fn render_read(html: &mut String, read: &ReadSummary) {
+    html.push_str("<h2>Capacity map</h2><p class=\"legend\">");
+    for (name, color) in CLASSES {
+        write!(
+            html,
+            "<span><i style=\"background:{}\"></i>{}</span>",
+            color, name
+        )
+        .unwrap();
+    }
+    html.push_str("</p><div class=\"map\">");
+    let mut cells: Vec<(i64, &str, String)> = read
+        .files
+        .iter()
+        .map(|f| (f.number, class_color(&f.stats), counts(&f.stats)))
+        .chain(
+            read.missing
+                .iter()
+                .map(|&n| (n, CLASSES[6].1, "missing".into())),
+        )
+        .collect();
+    cells.sort_by_key(|c| c.0);
+    for (n, color, title) in &cells {
+        write!(
+            html,
+            "<span style=\"background:{}\" title=\"{}.h2w: {}\"></span>",
+            color, n, title
+        )
+        .unwrap();
+    }
+    html.push_str("</div>\n");
+
+    let bad: Vec<i64> = cells
+        .iter()
+        .filter(|c| c.1 != CLASSES[0].1)
+        .map(|c| c.0)
+        .collect();
+    if !bad.is_empty() {
+        html.push_str("<h3>Bad ranges</h3><ul>");
+        for (first, last) in ranges(&bad) {
+            // File N holds GiB N-1 to N
+            write!(
+                html,
+                "<li>{}.h2w - {}.h2w (GiB {} to {})</li>",
+                first,
+                last,
+                first - 1,
+                last
+            )
+            .unwrap();
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("<h2>Files</h2><table><tr><th>File</th>");
+    for name in [
+        "ok",
+        "corrupted",
+        "changed",
+        "overwritten",
+        "stale",
+        "zeroed",
+        "ones",
+    ] {
+        write!(html, "<th>{}</th>", name).unwrap();
+    }
+    html.push_str("</tr>");
+    for f in &read.files {
+        let s = &f.stats;
+        write!(html, "<tr><td>{}.h2w</td>", f.number).unwrap();
+        for count in [
+            s.secs_ok(),
+            s.secs_corrupted(),
+            s.secs_changed(),
+            s.secs_overwritten(),
+            s.secs_stale(),
+            s.secs_zeroed(),
+            s.secs_ones(),
+        ] {
+            write!(html, "<td>{}</td>", count).unwrap();
+        }
+        html.push_str("</tr>");
+    }
+    for n in &read.missing {
+        write!(
+            html,
+            "<tr><td>{}.h2w</td><td colspan=\"7\">missing</td></tr>",
+            n
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n");
+
+    if !read.manifest_issues.is_empty() {
+        html.push_str("<h3>Manifest issues</h3><ul>");
+        for issue in &read.manifest_issues {
+            write!(html, "<li>{}</li>", escape(issue)).unwrap();
+        }
+        html.push_str("</ul>\n");
+    }
+}
+
+fn class_color(stats: &FileStats) -> &'static str {
+    // CLASSES is in the order of FileClass
+    CLASSES[stats.class() as usize].1
+}
+
+fn counts(s: &FileStats) -> String {
+    format!(
+        "{} ok, {} corrupted, {} changed, {} overwritten, {} stale, {} zeroed, {} ones",
+        s.secs_ok(),
+        s.secs_corrupted(),
+        s.secs_changed(),
+        s.secs_overwritten(),
+        s.secs_stale(),
+        s.secs_zeroed(),
+        s.secs_ones()
+    )
+}
+
+/// Runs of consecutive numbers in sorted `numbers`.
+fn ranges(numbers: &[i64]) -> Vec<(i64, i64)> {
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+    for &n in numbers {
+        match ranges.last_mut() {
+            Some(last) if last.1 + 1 == n => last.1 = n,
+            _ => ranges.push((n, n)),
+        }
+    }
+    ranges
+}
+
+fn bytes(count: u64) -> String {
+    let (size, unit) = adjust_unit(count as f64);
+    format!("{:.2} {}", size, unit)
+}
+
+fn bytes_or_dash(count: Option<u64>) -> String {
+    count.map_or_else(|| "-".into(), bytes)
+}
+
+fn speed_str(bps: f64) -> String {
+    if bps > 0.0 {
+        format!("{}/s", bytes(bps as u64))
+    } else {
+        "not available".into()
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
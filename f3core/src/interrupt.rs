@@ -0,0 +1,62 @@
+// f3core/src/interrupt.rs
+
+use std::{
+    io, process,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::{cursor::Show, execute};
+
+/// Exit code of a run stopped by SIGINT/SIGTERM (128 + SIGINT, like shells use).
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catch SIGINT/SIGTERM (Ctrl-C / Ctrl-Break on Windows): the first one asks the
+/// run to stop after the current chunk, a second one exits right away.
+pub fn install_handler() {
+    let res = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            let _ = execute!(io::stdout(), Show);
+            eprintln!("\nInterrupted again, exiting immediately");
+            process::exit(EXIT_INTERRUPTED);
+        }
+    });
+    if let Err(e) = res {
+        eprintln!("Warning: Failed to install the interrupt handler: {}", e);
+    }
+}
+
+/// True once the run has been asked to stop.
+#[inline]
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Ask the run to stop, as if SIGINT was received.
+pub fn request_stop() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Pause or resume the run at the next chunk (dashboard keys).
+pub fn set_paused(paused: bool) {
+    PAUSED.store(paused, Ordering::SeqCst);
+}
+
+#[inline]
+pub fn paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// Block while paused, unless asked to stop. Returns how long it waited.
+pub fn wait_while_paused() -> Duration {
+    let start = Instant::now();
+    while paused() && !interrupted() {
+        thread::sleep(Duration::from_millis(100));
+    }
+    start.elapsed()
+}
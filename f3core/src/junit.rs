@@ -0,0 +1,125 @@
+// f3core/src/junit.rs
+
+//! JUnit XML of the read results (`nf3_read --junit`), so CI shows every
+//! `.h2w` file as a test case.
+
+use std::fmt::Write as _;
+
+use crate::{html::escape, report::RunReport, verify::FileStats};
+
+pub fn render(report: &RunReport) -> String {
+    let suite = format!("nf3_read {}", report.path);
+    let mut cases = String::new();
+    let (mut tests, mut failures) = (0, 0);
+
+    let files = report.read.as_ref().map_or(&[][..], |r| &r.files[..]);
+    for f in files {
+        tests += 1;
+        write!(
+            cases,
+            "    <testcase classname=\"{}\" name=\"{}.h2w\"",
+            escape(&suite),
+            f.number
+        )
+        .unwrap();
+        if f.stats.secs_lost() == 0 {
+            cases.push_str("/>\n");
+        } else {
+            failures += 1;
+            writeln!(
+                cases,
+                ">\n      <failure type=\"DataLoss\" message=\"{}\"/>\n    </testcase>",
+                escape(&failure_message(&f.stats))
+            )
+            .unwrap();
+        }
+    }
+
+    let missing = report.read.as_ref().map_or(&[][..], |r| &r.missing[..]);
+    for n in missing {
+        tests += 1;
+        failures += 1;
+        writeln!(
+            cases,
+            "    <testcase classname=\"{}\" name=\"{}.h2w\">\n      <failure type=\"Missing\" message=\"{}.h2w is missing\"/>\n    </testcase>",
+            escape(&suite),
+            n,
+            n
+        )
+        .unwrap();
+    }
+
+    // Nothing was checked: fail the suite rather than report an empty pass
+    let mut errors = 0;
+    match &report.read {
+        None => {
+            tests += 1;
+            errors += 1;
+            writeln!(
+                cases,
+                "    <testcase classname=\"{}\" name=\"read\">\n      <error type=\"ReadError\" message=\"the files could not be read\"/>\n    </testcase>",
+                escape(&suite)
+            )
+            .unwrap();
+        }
+        Some(_) if tests == 0 => {
+            tests += 1;
+            failures += 1;
+            writeln!(
+                cases,
+                "    <testcase classname=\"{}\" name=\"files\">\n      <failure type=\"NoFiles\" message=\"no .h2w files found\"/>\n    </testcase>",
+                escape(&suite)
+            )
+            .unwrap();
+        }
+        Some(_) => {}
+    }
+
+    let seconds = report.read.as_ref().map_or(0, |r| r.elapsed_ms) as f64 / 1000.0;
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        xml,
+        "<testsuites tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" time=\"{seconds:.3}\">"
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "  <testsuite name=\"{}\" hostname=\"{}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" skipped=\"0\" time=\"{seconds:.3}\">",
+        escape(&suite),
+        escape(&report.host)
+    )
+    .unwrap();
+    xml.push_str(&cases);
+
+    let mut out = Vec::new();
+    if report.interrupted {
+        out.push("Interrupted, the results are partial".to_string());
+    }
+    if let Some(read) = &report.read {
+        out.extend(read.manifest_issues.iter().cloned());
+    }
+    if !out.is_empty() {
+        writeln!(
+            xml,
+            "    <system-out>{}</system-out>",
+            escape(&out.join("\n"))
+        )
+        .unwrap();
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn failure_message(s: &FileStats) -> String {
+    format!(
+        "{} of {} sectors not ok: {} corrupted, {} changed, {} overwritten, {} stale, {} zeroed, {} ones",
+        s.secs_lost(),
+        s.secs_ok() + s.secs_lost(),
+        s.secs_corrupted(),
+        s.secs_changed(),
+        s.secs_overwritten(),
+        s.secs_stale(),
+        s.secs_zeroed(),
+        s.secs_ones()
+    )
+}
@@ -1,9 +1,25 @@
 // f3core/src/lib.rs
 pub mod cli;
+pub mod dashboard;
+pub mod device;
 pub mod file_fill;
 pub mod flow;
+pub mod guard;
+pub mod history;
+pub mod html;
+pub mod interrupt;
+pub mod junit;
+pub mod lock;
+pub mod manifest;
+pub mod pattern;
+pub mod probe;
+pub mod report;
+pub mod speed;
+pub mod state;
+#[cfg(all(feature = "tui", unix))]
+mod tui;
 pub mod utils;
 pub mod verify;
 
-// #[cfg(test)]
+#[cfg(any(test, feature = "test-api"))]
 pub mod tests;
@@ -0,0 +1,119 @@
+// f3core/src/lock.rs
+
+//! Advisory lock on the target directory, so nf3_write and nf3_read don't
+//! run against the same drive at once.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Result, Seek, Write},
+    path::Path,
+    process, thread,
+    time::Duration,
+};
+
+use crate::interrupt::interrupted;
+
+pub const LOCK_NAME: &str = ".nf3.lock";
+
+/// Held for the duration of a run; the lock is released when dropped (or
+/// when the process exits). The file itself is left in place.
+#[derive(Debug)]
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// Lock `<dir>/.nf3.lock` for `tool`. If another run holds it, fail
+    /// with `WouldBlock` naming the holder, or with `wait` block until it is
+    /// released (or the run is interrupted).
+    pub fn acquire(dir: &str, tool: &str, wait: bool) -> Result<RunLock> {
+        let path = Path::new(dir).join(LOCK_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        wait_for(&mut file, dir, wait)?;
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{} (pid {})", tool, process::id())?;
+        Ok(RunLock { _file: file })
+    }
+
+    /// Like `acquire`, for nf3_read: a drive mounted read-only (or without
+    /// write permission) is locked through the existing lock file, or `dir`
+    /// itself, without recording the holder.
+    pub fn acquire_for_reading(dir: &str, tool: &str, wait: bool) -> Result<RunLock> {
+        match Self::acquire(dir, tool, wait) {
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::ReadOnlyFilesystem | io::ErrorKind::PermissionDenied
+                ) =>
+            {
+                Self::acquire_without_writing(dir, wait)
+            }
+            res => res,
+        }
+    }
+
+    pub(crate) fn acquire_without_writing(dir: &str, wait: bool) -> Result<RunLock> {
+        let mut file = File::open(Path::new(dir).join(LOCK_NAME)).or_else(|_| File::open(dir))?;
+        wait_for(&mut file, dir, wait)?;
+        Ok(RunLock { _file: file })
+    }
+}
+
+/// Take the lock of `file`, see `RunLock::acquire`.
+fn wait_for(file: &mut File, dir: &str, wait: bool) -> Result<()> {
+    if try_lock(file)? {
+        return Ok(());
+    }
+    // A directory has no holder to read
+    let mut holder = String::new();
+    let _ = file.read_to_string(&mut holder);
+    let holder = match holder.trim() {
+        "" => "another NeoF3 run".to_string(),
+        h => h.to_string(),
+    };
+    if !wait {
+        return Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            format!("{} is in use by {}", dir, holder),
+        ));
+    }
+    println!("Waiting for {} to finish with {}...", holder, dir);
+    while !try_lock(file)? {
+        if interrupted() {
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "interrupted while waiting for the lock",
+            ));
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    Ok(())
+}
+
+/// `flock(LOCK_EX | LOCK_NB)`: false if someone else holds the lock.
+fn try_lock(file: &File) -> Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsRawFd;
+
+        let rc = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+        if rc != 0 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::EWOULDBLOCK) => Ok(false),
+                _ => Err(err),
+            };
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = file;
+
+    Ok(true)
+}
@@ -0,0 +1,253 @@
+// f3core/src/manifest.rs
+
+use std::{
+    fmt, fs,
+    io::Result,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    pattern::{ByteOrder, GeneratorKind, Pattern, PatternHint},
+    report::WriteSummary,
+    speed::SpeedSample,
+    utils::{GIB, host_name, new_run_seed, read_json, write_json},
+    verify::TOLERANCE,
+};
+
+fn default_tolerance() -> usize {
+    TOLERANCE
+}
+
+fn default_start_at() -> i64 {
+    1
+}
+
+pub const MANIFEST_NAME: &str = "nf3-manifest.json";
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Sidecar file written by nf3_write next to the .h2w files, describing
+/// how they were produced so nf3_read doesn't have to guess.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub run_id: String,
+    pub file_size: u64,
+    pub sector_size: usize,
+    #[serde(default = "default_tolerance")]
+    pub tolerance: usize,
+    pub generator: GeneratorKind,
+    pub seed: u64,
+    pub byte_order: ByteOrder,
+    /// Written with `--quick`: only one marker sector per MARKER_SPACING.
+    #[serde(default)]
+    pub quick: bool,
+    pub host: String,
+    /// Unix time (seconds) the run started at.
+    pub started_at: u64,
+    /// Range of files the run wrote (`end_at` 0 = no limit), files outside
+    /// it belong to earlier runs and are not checked.
+    #[serde(default = "default_start_at")]
+    pub start_at: i64,
+    #[serde(default)]
+    pub end_at: i64,
+    pub files: Vec<ManifestFile>,
+    /// Write speed over capacity, charted next to the read speed by nf3_read.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub write_speeds: Vec<SpeedSample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub number: i64,
+    pub size: u64,
+}
+
+/// Disagreement between the manifest and the files on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestIssue {
+    Missing(i64),
+    Extra(i64),
+    SizeMismatch {
+        number: i64,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for ManifestIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestIssue::Missing(n) => {
+                write!(f, "{}.h2w is listed in the manifest but missing", n)
+            }
+            ManifestIssue::Extra(n) => write!(f, "{}.h2w is not listed in the manifest", n),
+            ManifestIssue::SizeMismatch {
+                number,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}.h2w has {} bytes, the manifest expects {}",
+                number, actual, expected
+            ),
+        }
+    }
+}
+
+impl Manifest {
+    pub fn new(pattern: &Pattern) -> Self {
+        Manifest {
+            version: MANIFEST_VERSION,
+            run_id: format!("{:016x}", new_run_seed()),
+            file_size: GIB,
+            sector_size: pattern.sector_size,
+            tolerance: pattern.tolerance,
+            generator: pattern.kind,
+            seed: pattern.seed,
+            byte_order: pattern.order,
+            quick: false,
+            host: host_name(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            start_at: 1,
+            end_at: 0,
+            files: Vec::new(),
+            write_speeds: Vec::new(),
+        }
+    }
+
+    /// Load the manifest of `dir`, `Ok(None)` if there is none.
+    pub fn load(dir: &str) -> Result<Option<Manifest>> {
+        let Some(manifest) = read_json::<Manifest>(&Path::new(dir).join(MANIFEST_NAME))? else {
+            return Ok(None);
+        };
+        if manifest.version > MANIFEST_VERSION {
+            eprintln!(
+                "Warning: {} has version {}, this build understands up to {}",
+                MANIFEST_NAME, manifest.version, MANIFEST_VERSION
+            );
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Write the manifest into `dir`.
+    pub fn save(&self, dir: &str) -> Result<()> {
+        write_json(&Path::new(dir).join(MANIFEST_NAME), self)
+    }
+
+    /// Record file `number` with `size`, `update_sizes` puts them in order.
+    pub fn record_file(&mut self, number: i64, size: u64) {
+        self.files.push(ManifestFile { number, size });
+    }
+
+    /// Replace the recorded sizes with the sizes of the files in `dir`,
+    /// dropping files that were never created.
+    pub fn update_sizes(&mut self, dir: &str) {
+        self.files.sort_by_key(|f| f.number);
+        self.files.dedup_by_key(|f| f.number);
+        self.files.retain_mut(|file| {
+            let full = Path::new(dir).join(format!("{}.h2w", file.number));
+            match fs::metadata(full) {
+                Ok(meta) => {
+                    file.size = meta.len();
+                    true
+                }
+                Err(_) => false,
+            }
+        });
+    }
+
+    pub fn pattern(&self) -> Pattern {
+        Pattern::new(self.generator, self.seed)
+            .with_order(self.byte_order)
+            .with_sector_size(self.sector_size)
+            .with_tolerance(self.tolerance)
+    }
+
+    /// Fill what the user did not specify in `hint` from the manifest.
+    pub fn apply_to(&self, hint: &mut PatternHint) {
+        hint.kind = hint.kind.or(Some(self.generator));
+        hint.order = hint.order.or(Some(self.byte_order));
+        hint.sector_size = hint.sector_size.or(Some(self.sector_size));
+        hint.tolerance = hint.tolerance.or(Some(self.tolerance));
+        if hint.seed == 0 {
+            hint.seed = self.seed;
+        }
+    }
+
+    /// Write side of the run as far as the manifest tells it, for the report
+    /// of nf3_read: speed and time come from `write_speeds`, a fake capacity
+    /// found by a spot check is not recorded.
+    pub fn write_summary(&self) -> WriteSummary {
+        // Time taken by each stretch between two samples at its speed
+        let mut secs = 0.0;
+        let mut prev = 0;
+        for s in self.write_speeds.iter().filter(|s| s.speed > 0.0) {
+            secs += (s.offset - prev) as f64 / s.speed;
+            prev = s.offset;
+        }
+        WriteSummary {
+            start_at: self.start_at,
+            end_at: self.end_at,
+            last_completed: self
+                .files
+                .iter()
+                .filter(|f| f.size == self.file_size)
+                .map(|f| f.number)
+                .max(),
+            bytes_written: self.files.iter().map(|f| f.size).sum(),
+            avg_speed: if secs > 0.0 { prev as f64 / secs } else { 0.0 },
+            elapsed_ms: (secs * 1000.0) as u64,
+            fake_capacity_at: None,
+            write_error: false,
+            quick: self.quick,
+            sector_size: self.sector_size,
+            speeds: self.write_speeds.clone(),
+        }
+    }
+
+    /// Compare the manifest with `files` found in `dir`, looking only at
+    /// numbers from `start_at` to `end_at` (0 = no upper limit) that the
+    /// run wrote.
+    pub fn check(
+        &self,
+        dir: &str,
+        files: &[i64],
+        start_at: i64,
+        end_at: i64,
+    ) -> Vec<ManifestIssue> {
+        let within = |n: i64, first: i64, last: i64| n >= first && (n <= last || last == 0);
+        let in_range =
+            |n: i64| within(n, start_at, end_at) && within(n, self.start_at, self.end_at);
+        let mut issues = Vec::new();
+
+        for file in self.files.iter().filter(|f| in_range(f.number)) {
+            if !files.contains(&file.number) {
+                issues.push(ManifestIssue::Missing(file.number));
+                continue;
+            }
+            let full = Path::new(dir).join(format!("{}.h2w", file.number));
+            let actual = fs::metadata(full).map(|m| m.len()).unwrap_or_default();
+            if actual != file.size {
+                issues.push(ManifestIssue::SizeMismatch {
+                    number: file.number,
+                    expected: file.size,
+                    actual,
+                });
+            }
+        }
+
+        for &n in files.iter().filter(|&&n| in_range(n)) {
+            if !self.files.iter().any(|f| f.number == n) {
+                issues.push(ManifestIssue::Extra(n));
+            }
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,232 @@
+// f3core/src/pattern.rs
+
+use chacha20::{
+    ChaCha20,
+    cipher::{KeyIvInit, StreamCipher},
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    utils::{SECTOR_SIZE, random_number},
+    verify::TOLERANCE,
+};
+
+/// Source of the pseudorandom data written into every sector.
+///
+/// Implementations must be deterministic: the content of a sector depends
+/// only on its offset and the run seed. Word 0 of every sector holds the
+/// offset and, when `seed != 0`, word 1 holds the seed; the reader relies on
+/// both to detect overwritten and stale sectors. Words are little-endian,
+/// `Pattern` takes care of other byte orders.
+pub trait PatternGenerator {
+    /// Fill `sector` (a multiple of 8 bytes, the sector size of the run)
+    /// for the sector at `offset`.
+    fn fill_sector(&self, sector: &mut [u8], offset: u64, seed: u64);
+}
+
+/// The original f3 pattern: a chain of `random_number` starting from the offset.
+pub struct LcgGenerator;
+
+impl PatternGenerator for LcgGenerator {
+    fn fill_sector(&self, sector: &mut [u8], offset: u64, seed: u64) {
+        let mut prev = offset ^ seed;
+        for (i, word) in sector.chunks_exact_mut(8).enumerate() {
+            word.copy_from_slice(&pattern_word(i, offset, seed, prev).to_le_bytes());
+            prev = random_number(prev);
+        }
+    }
+}
+
+/// Expected value of word `i` of an LCG sector whose header is `offset`,
+/// `chained` being the current value of the pseudorandom sequence.
+#[inline]
+pub fn pattern_word(i: usize, offset: u64, seed: u64, chained: u64) -> u64 {
+    match i {
+        0 => offset,
+        1 if seed != 0 => seed,
+        _ => chained,
+    }
+}
+
+/// ChaCha20 keystream keyed by the seed, with the sector offset as nonce.
+/// Much harder for a controller to recognise than the LCG.
+pub struct ChaChaGenerator;
+
+const CHACHA_KEY_TAG: &[u8; 24] = b"NeoF3 sector pattern v1\0";
+
+impl PatternGenerator for ChaChaGenerator {
+    fn fill_sector(&self, sector: &mut [u8], offset: u64, seed: u64) {
+        let mut key = [0u8; 32];
+        key[..8].copy_from_slice(&seed.to_le_bytes());
+        key[8..].copy_from_slice(CHACHA_KEY_TAG);
+        let mut nonce = [0u8; 12];
+        nonce[..8].copy_from_slice(&offset.to_le_bytes());
+
+        sector.fill(0);
+        ChaCha20::new(&key.into(), &nonce.into()).apply_keystream(sector);
+
+        sector[..8].copy_from_slice(&offset.to_le_bytes());
+        if seed != 0 {
+            sector[8..16].copy_from_slice(&seed.to_le_bytes());
+        }
+    }
+}
+
+/// Generators selectable from the command line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorKind {
+    /// Original f3 linear congruential generator (compatible default)
+    #[default]
+    Lcg,
+    /// ChaCha20 keystream keyed by the run seed
+    Chacha,
+}
+
+impl GeneratorKind {
+    pub const ALL: [GeneratorKind; 2] = [GeneratorKind::Lcg, GeneratorKind::Chacha];
+
+    pub fn generator(self) -> &'static dyn PatternGenerator {
+        match self {
+            GeneratorKind::Lcg => &LcgGenerator,
+            GeneratorKind::Chacha => &ChaChaGenerator,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GeneratorKind::Lcg => "lcg",
+            GeneratorKind::Chacha => "chacha",
+        }
+    }
+}
+
+/// Byte order of the 64-bit words on disk.
+///
+/// The format is little-endian. Files written by older versions on a
+/// big-endian host used native order, `Big` reads them back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteOrder {
+    #[default]
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    pub const ALL: [ByteOrder; 2] = [ByteOrder::Little, ByteOrder::Big];
+
+    #[inline]
+    pub fn read_word(self, bytes: &[u8]) -> u64 {
+        let mut arr = [0u8; 8];
+        arr.copy_from_slice(&bytes[..8]);
+        match self {
+            ByteOrder::Little => u64::from_le_bytes(arr),
+            ByteOrder::Big => u64::from_be_bytes(arr),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ByteOrder::Little => "little",
+            ByteOrder::Big => "big",
+        }
+    }
+}
+
+/// What the reader knows about the pattern before looking at the files:
+/// `None` kind and order are detected from the data, `None` sector size
+/// and tolerance fall back to the defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternHint {
+    pub kind: Option<GeneratorKind>,
+    pub seed: u64,
+    pub order: Option<ByteOrder>,
+    pub sector_size: Option<usize>,
+    pub tolerance: Option<usize>,
+}
+
+impl PatternHint {
+    /// Pattern of this hint with the given generator and byte order.
+    pub fn pattern(&self, kind: GeneratorKind, order: ByteOrder) -> Pattern {
+        Pattern::new(kind, self.seed)
+            .with_order(order)
+            .with_sector_size(self.sector_size.unwrap_or(SECTOR_SIZE))
+            .with_tolerance(self.tolerance.unwrap_or(TOLERANCE))
+    }
+
+    /// Pattern to assume when detection fails.
+    pub fn fallback(&self) -> Pattern {
+        self.pattern(
+            self.kind.unwrap_or_default(),
+            self.order.unwrap_or_default(),
+        )
+    }
+}
+
+/// Everything that determines the data of a run: the generator, the seed,
+/// the byte order of the words and the sector size, plus the number of
+/// words a sector may differ by and still count as "changed".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pattern {
+    pub kind: GeneratorKind,
+    pub seed: u64,
+    pub order: ByteOrder,
+    #[serde(default = "default_sector_size")]
+    pub sector_size: usize,
+    #[serde(default = "default_tolerance")]
+    pub tolerance: usize,
+}
+
+fn default_sector_size() -> usize {
+    SECTOR_SIZE
+}
+
+fn default_tolerance() -> usize {
+    TOLERANCE
+}
+
+impl Default for Pattern {
+    fn default() -> Self {
+        Pattern::new(GeneratorKind::default(), 0)
+    }
+}
+
+impl Pattern {
+    pub fn new(kind: GeneratorKind, seed: u64) -> Self {
+        Self {
+            kind,
+            seed,
+            order: ByteOrder::Little,
+            sector_size: SECTOR_SIZE,
+            tolerance: TOLERANCE,
+        }
+    }
+
+    pub fn with_order(mut self, order: ByteOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_sector_size(mut self, sector_size: usize) -> Self {
+        self.sector_size = sector_size;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: usize) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    #[inline]
+    pub fn fill_sector(&self, sector: &mut [u8], offset: u64) {
+        debug_assert_eq!(sector.len(), self.sector_size);
+        self.kind.generator().fill_sector(sector, offset, self.seed);
+        if self.order == ByteOrder::Big {
+            for word in sector.chunks_exact_mut(8) {
+                word.reverse();
+            }
+        }
+    }
+}
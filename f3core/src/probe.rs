@@ -0,0 +1,96 @@
+// f3core/src/probe.rs
+
+use std::{
+    fs::File,
+    io::{Read, Result, Seek, SeekFrom, Write},
+};
+
+use crate::{
+    file_fill::fill_buffer,
+    pattern::Pattern,
+    utils::{SECTOR_SIZE, fadvise_dontneed},
+    verify::{FileStats, check_buffer},
+};
+
+/// Size of a single probing block.
+pub const PROBE_BLOCK_SIZE: usize = 1024 * 1024; // 1 MiB
+
+/// Something the cache probe can write to, flush and read back.
+///
+/// `drop_cache` must push written data to the device and make sure that
+/// the following reads are not served from the host (page) cache,
+/// otherwise the probe would only measure the host memory.
+pub trait ProbeTarget: Read + Write + Seek {
+    fn drop_cache(&mut self) -> Result<()>;
+}
+
+impl ProbeTarget for File {
+    fn drop_cache(&mut self) -> Result<()> {
+        self.sync_data()?;
+        fadvise_dontneed(self)
+    }
+}
+
+/// Outcome of `probe_cache_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheSize {
+    /// Earliest blocks vanished once more than `bytes` were written.
+    Detected { bytes: u64 },
+    /// Every round up to `tested` bytes read back intact.
+    NotDetected { tested: u64 },
+}
+
+/// Measure the write-back cache of the device behind `target`, like f3probe does.
+///
+/// Each round writes twice as many blocks of pattern data as the previous one
+/// (starting from one block) at the beginning of `target`, drops the caches and
+/// reads back the earliest block. A device that only keeps data in its cache
+/// returns it fine until the cache overflows, so the size of the last round
+/// whose earliest block survived is the size of the cache.
+///
+/// Every round uses its own pattern offsets, so data left by a previous
+/// round does not count as a good read.
+pub fn probe_cache_size<T: ProbeTarget>(
+    target: &mut T,
+    block_size: usize,
+    max_size: u64,
+) -> Result<CacheSize> {
+    assert!(
+        block_size > 0 && block_size.is_multiple_of(SECTOR_SIZE),
+        "block size must be a positive multiple of SECTOR_SIZE"
+    );
+
+    let mut buf = vec![0u8; block_size];
+    let mut blocks: u64 = 1;
+    let mut last_good: u64 = 0;
+    let mut round: u64 = 1;
+
+    while blocks * block_size as u64 <= max_size {
+        // High bits keep offsets of different rounds apart
+        let base = round << 48;
+
+        target.seek(SeekFrom::Start(0))?;
+        let mut offset = base;
+        for _ in 0..blocks {
+            offset = fill_buffer(&mut buf, block_size, offset, &Pattern::default());
+            target.write_all(&buf)?;
+        }
+        target.drop_cache()?;
+
+        target.seek(SeekFrom::Start(0))?;
+        target.read_exact(&mut buf)?;
+        let mut stats = FileStats::new();
+        check_buffer(&buf, block_size, base, &Pattern::default(), &mut stats);
+
+        if stats.secs_ok() * (SECTOR_SIZE as u64) < block_size as u64 {
+            // `last_good == 0` means that even a single block did not survive
+            return Ok(CacheSize::Detected { bytes: last_good });
+        }
+
+        last_good = blocks * block_size as u64;
+        blocks *= 2;
+        round += 1;
+    }
+
+    Ok(CacheSize::NotDetected { tested: last_good })
+}
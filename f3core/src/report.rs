@@ -0,0 +1,280 @@
+// f3core/src/report.rs
+
+use std::{
+    fmt, fs,
+    io::{self, Result},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    device::DeviceInfo,
+    file_fill::MARKER_SPACING,
+    html, junit,
+    speed::SpeedSample,
+    utils::{SECTOR_SIZE, adjust_unit, fs_space, host_name},
+    verify::FileStats,
+};
+
+/// Machine-readable summary of a run, written with `--report PATH`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub version: String,
+    pub host: String,
+    pub path: String,
+    pub run_id: Option<String>,
+    /// Unix time (seconds) the run started at.
+    pub started_at: u64,
+    /// Mount and block device behind `path`, see `crate::device`.
+    #[serde(default)]
+    pub device: Option<DeviceInfo>,
+    /// Size and free space of the filesystem when the run started.
+    #[serde(default)]
+    pub fs_size: Option<u64>,
+    #[serde(default)]
+    pub fs_free: Option<u64>,
+    /// The run was stopped by SIGINT/SIGTERM, results are partial.
+    pub interrupted: bool,
+    pub write: Option<WriteSummary>,
+    pub read: Option<ReadSummary>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WriteSummary {
+    pub start_at: i64,
+    pub end_at: i64,
+    /// Last file that was completely written.
+    pub last_completed: Option<i64>,
+    pub bytes_written: u64,
+    /// Average speed in bytes per second, 0 if not available.
+    pub avg_speed: f64,
+    pub elapsed_ms: u64,
+    /// Bytes written when a spot check found earlier data lost, i.e. the
+    /// real capacity of a fake drive. The run was stopped there.
+    #[serde(default)]
+    pub fake_capacity_at: Option<u64>,
+    /// Stopped by a write error other than a full drive.
+    #[serde(default)]
+    pub write_error: bool,
+    /// Written with `--quick`: the speed is meaningless.
+    #[serde(default)]
+    pub quick: bool,
+    #[serde(default = "default_sector_size")]
+    pub sector_size: usize,
+    /// Speed over capacity, see `crate::speed`.
+    #[serde(default)]
+    pub speeds: Vec<SpeedSample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadSummary {
+    pub files: Vec<FileResult>,
+    pub missing: Vec<i64>,
+    pub manifest_issues: Vec<String>,
+    pub totals: FileStats,
+    /// Average speed in bytes per second, 0 if not available.
+    pub avg_speed: f64,
+    pub elapsed_ms: u64,
+    /// Set with `--sample`: `totals` then count sampled sectors only.
+    #[serde(default)]
+    pub estimate: Option<SampleEstimate>,
+    /// Files of a quick run: `totals` count marker sectors only.
+    #[serde(default)]
+    pub quick: bool,
+    #[serde(default = "default_sector_size")]
+    pub sector_size: usize,
+    /// Speed over capacity, see `crate::speed`.
+    #[serde(default)]
+    pub speeds: Vec<SpeedSample>,
+}
+
+fn default_sector_size() -> usize {
+    SECTOR_SIZE
+}
+
+impl Default for ReadSummary {
+    fn default() -> Self {
+        ReadSummary {
+            files: Vec::new(),
+            missing: Vec::new(),
+            manifest_issues: Vec::new(),
+            totals: FileStats::new(),
+            avg_speed: 0.0,
+            elapsed_ms: 0,
+            estimate: None,
+            quick: false,
+            sector_size: SECTOR_SIZE,
+            speeds: Vec::new(),
+        }
+    }
+}
+
+/// Data loss extrapolated from sampled sectors to the whole run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SampleEstimate {
+    pub samples: u64,
+    pub total_bytes: u64,
+    pub lost_bytes: u64,
+    /// 95% confidence interval of `lost_bytes`.
+    pub lost_low: u64,
+    pub lost_high: u64,
+}
+
+impl SampleEstimate {
+    /// Extrapolate `totals` of sampled sectors to `total_bytes`, with the
+    /// Wilson score interval of the lost fraction.
+    pub fn new(totals: &FileStats, total_bytes: u64) -> Self {
+        let n = (totals.secs_ok() + totals.secs_lost()) as f64;
+        if n == 0.0 {
+            return SampleEstimate {
+                total_bytes,
+                lost_high: total_bytes,
+                ..Default::default()
+            };
+        }
+
+        const Z: f64 = 1.96;
+        let p = totals.secs_lost() as f64 / n;
+        let center = (p + Z * Z / (2.0 * n)) / (1.0 + Z * Z / n);
+        let half = Z / (1.0 + Z * Z / n) * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt();
+        let bytes = |f: f64| (f.clamp(0.0, 1.0) * total_bytes as f64) as u64;
+
+        SampleEstimate {
+            samples: n as u64,
+            total_bytes,
+            lost_bytes: bytes(p),
+            lost_low: bytes(center - half),
+            lost_high: bytes(center + half),
+        }
+    }
+
+    /// Estimated bytes of the whole run for `count` sampled sectors.
+    pub fn scale(&self, count: u64) -> u64 {
+        if self.samples == 0 {
+            return 0;
+        }
+        (count as f64 / self.samples as f64 * self.total_bytes as f64) as u64
+    }
+}
+
+/// Verification results of a single `<number>.h2w`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileResult {
+    pub number: i64,
+    pub stats: FileStats,
+}
+
+impl ReadSummary {
+    /// Some data didn't read back as written, or files are missing.
+    pub fn data_lost(&self) -> bool {
+        self.totals.secs_lost() > 0 || !self.missing.is_empty()
+    }
+
+    /// Bytes of the run `count` checked sectors stand for.
+    pub fn scaled_bytes(&self, count: u64) -> u64 {
+        match self.estimate {
+            Some(e) => e.scale(count),
+            None if self.quick => count * MARKER_SPACING,
+            None => count * self.sector_size as u64,
+        }
+    }
+}
+
+/// Outcome of a run, as shown in the HTML report and the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    Ok,
+    Failed,
+    /// A spot check lost data after this many bytes were written.
+    FakeCapacity(u64),
+    Incomplete,
+    NotVerified,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Ok => write!(f, "OK: all data read back as written"),
+            Verdict::Failed => write!(f, "FAILED: the drive lost data"),
+            Verdict::FakeCapacity(at) => {
+                let (size, unit) = adjust_unit(*at as f64);
+                write!(
+                    f,
+                    "FAKE CAPACITY: data is lost beyond ~{:.2} {}",
+                    size, unit
+                )
+            }
+            Verdict::Incomplete => write!(f, "INCOMPLETE: the run was interrupted"),
+            Verdict::NotVerified => write!(f, "NOT VERIFIED: the files were not read back"),
+        }
+    }
+}
+
+impl RunReport {
+    pub fn verdict(&self) -> Verdict {
+        if let Some(at) = self.write.as_ref().and_then(|w| w.fake_capacity_at) {
+            return Verdict::FakeCapacity(at);
+        }
+        match &self.read {
+            Some(read) if read.data_lost() => Verdict::Failed,
+            _ if self.interrupted => Verdict::Incomplete,
+            Some(_) => Verdict::Ok,
+            None => Verdict::NotVerified,
+        }
+    }
+
+    /// Report of a run on `path`; the target itself is looked at by
+    /// `describe_target`.
+    pub fn new(path: &str) -> Self {
+        RunReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            host: host_name(),
+            path: path.to_string(),
+            run_id: None,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            device: None,
+            fs_size: None,
+            fs_free: None,
+            interrupted: false,
+            write: None,
+            read: None,
+        }
+    }
+
+    /// Record `device` (see `DeviceInfo::probe`) and the current size and
+    /// free space of the filesystem of `path`.
+    pub fn describe_target(&mut self, device: Option<DeviceInfo>) {
+        let space = fs_space(&self.path);
+        self.device = device;
+        self.fs_size = space.map(|s| s.0);
+        self.fs_free = space.map(|s| s.1);
+    }
+
+    pub fn save(&self, file: &str) -> Result<()> {
+        let data = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(file, data)
+    }
+
+    /// Self-contained HTML version of the report, see `crate::html`.
+    pub fn to_html(&self) -> String {
+        html::render(self)
+    }
+
+    pub fn save_html(&self, file: &str) -> Result<()> {
+        fs::write(file, self.to_html())
+    }
+
+    /// JUnit XML of the read results, see `crate::junit`.
+    pub fn save_junit(&self, file: &str) -> Result<()> {
+        fs::write(file, junit::render(self))
+    }
+
+    pub fn load(file: &str) -> Result<RunReport> {
+        let data = fs::read_to_string(file)?;
+        serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
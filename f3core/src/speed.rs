@@ -0,0 +1,201 @@
+// f3core/src/speed.rs
+
+//! Speed over capacity, h2testw style: where a drive slows down shows SLC
+//! cache exhaustion and thermal throttling.
+
+use std::{fmt::Write as _, fs, io::Result};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::GIB;
+
+/// Samples kept per run; beyond that neighbours are averaged in pairs, so a
+/// long run keeps its full extent at a coarser resolution.
+pub const MAX_SAMPLES: usize = 2048;
+
+/// Throughput measured by `Flow::measure` once `offset` bytes were processed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpeedSample {
+    pub offset: u64,
+    /// Bytes per second.
+    pub speed: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpeedLog {
+    samples: Vec<SpeedSample>,
+}
+
+impl SpeedLog {
+    pub fn push(&mut self, offset: u64, speed: f64) {
+        if !speed.is_finite() {
+            return;
+        }
+        self.samples.push(SpeedSample { offset, speed });
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples = self
+                .samples
+                .chunks(2)
+                .map(|pair| SpeedSample {
+                    offset: pair[pair.len() - 1].offset,
+                    speed: pair.iter().map(|s| s.speed).sum::<f64>() / pair.len() as f64,
+                })
+                .collect();
+        }
+    }
+
+    pub fn samples(&self) -> &[SpeedSample] {
+        &self.samples
+    }
+}
+
+/// `phase,offset_bytes,speed_bytes_per_s` rows, write samples first.
+pub fn to_csv(write: &[SpeedSample], read: &[SpeedSample]) -> String {
+    let mut csv = String::from("phase,offset_bytes,speed_bytes_per_s\n");
+    for (phase, samples) in [("write", write), ("read", read)] {
+        for s in samples {
+            writeln!(csv, "{},{},{:.0}", phase, s.offset, s.speed).unwrap();
+        }
+    }
+    csv
+}
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 400.0;
+const LEFT: f64 = 70.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 40.0;
+const BOTTOM: f64 = 50.0;
+const WRITE_COLOR: &str = "#d9480f";
+const READ_COLOR: &str = "#1971c2";
+
+/// Self-contained SVG line chart of speed (MB/s) over capacity (GiB).
+pub fn to_svg(write: &[SpeedSample], read: &[SpeedSample]) -> String {
+    let all = write.iter().chain(read);
+    let max_offset = all.clone().map(|s| s.offset).max().unwrap_or(0).max(1);
+    let max_speed = all.map(|s| s.speed).fold(0.0, f64::max).max(1.0) / 1e6;
+    let (x_step, x_max) = axis(max_offset as f64 / GIB as f64);
+    let (y_step, y_max) = axis(max_speed);
+
+    let plot_w = WIDTH - LEFT - RIGHT;
+    let plot_h = HEIGHT - TOP - BOTTOM;
+    let x = |offset: u64| LEFT + offset as f64 / GIB as f64 / x_max * plot_w;
+    let y = |speed: f64| TOP + plot_h - speed / 1e6 / y_max * plot_h;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+        w = WIDTH,
+        h = HEIGHT
+    )
+    .unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="24" font-size="16" text-anchor="middle">Speed over capacity</text>"#,
+        WIDTH / 2.0
+    )
+    .unwrap();
+
+    let mut tick = 0.0;
+    while tick <= y_max + y_step / 2.0 {
+        let ty = TOP + plot_h - tick / y_max * plot_h;
+        writeln!(
+            svg,
+            r##"<line x1="{LEFT}" y1="{ty:.1}" x2="{:.1}" y2="{ty:.1}" stroke="#ddd"/><text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"##,
+            WIDTH - RIGHT,
+            LEFT - 6.0,
+            ty + 4.0,
+            label(tick)
+        )
+        .unwrap();
+        tick += y_step;
+    }
+    let mut tick = 0.0;
+    while tick <= x_max + x_step / 2.0 {
+        let tx = LEFT + tick / x_max * plot_w;
+        writeln!(
+            svg,
+            r##"<line x1="{tx:.1}" y1="{TOP}" x2="{tx:.1}" y2="{:.1}" stroke="#ddd"/><text x="{tx:.1}" y="{:.1}" text-anchor="middle">{}</text>"##,
+            TOP + plot_h,
+            TOP + plot_h + 18.0,
+            label(tick)
+        )
+        .unwrap();
+        tick += x_step;
+    }
+    writeln!(
+        svg,
+        r#"<rect x="{LEFT}" y="{TOP}" width="{plot_w}" height="{plot_h}" fill="none" stroke="black"/>"#
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">Position (GiB)</text>"#,
+        LEFT + plot_w / 2.0,
+        HEIGHT - 12.0
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text transform="translate(18 {:.1}) rotate(-90)" text-anchor="middle">Speed (MB/s)</text>"#,
+        TOP + plot_h / 2.0
+    )
+    .unwrap();
+
+    let mut legend_x = LEFT + 10.0;
+    for (name, color, samples) in [("write", WRITE_COLOR, write), ("read", READ_COLOR, read)] {
+        if samples.is_empty() {
+            continue;
+        }
+        let points: Vec<String> = samples
+            .iter()
+            .map(|s| format!("{:.1},{:.1}", x(s.offset), y(s.speed)))
+            .collect();
+        writeln!(
+            svg,
+            r#"<polyline fill="none" stroke="{}" stroke-width="1.5" points="{}"/>"#,
+            color,
+            points.join(" ")
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<rect x="{legend_x:.1}" y="{:.1}" width="12" height="3" fill="{color}"/><text x="{:.1}" y="{:.1}">{name}</text>"#,
+            TOP + 12.0,
+            legend_x + 16.0,
+            TOP + 17.0
+        )
+        .unwrap();
+        legend_x += 70.0;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A round tick step for values up to `max` and the axis end it gives.
+fn axis(max: f64) -> (f64, f64) {
+    let raw = max / 5.0;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|s| *s >= raw)
+        .unwrap_or(10.0 * magnitude);
+    (step, (max / step).ceil().max(1.0) * step)
+}
+
+fn label(value: f64) -> String {
+    if value.fract().abs() < 1e-9 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+/// Write `<prefix>.csv` and `<prefix>.svg`.
+pub fn export(prefix: &str, write: &[SpeedSample], read: &[SpeedSample]) -> Result<()> {
+    fs::write(format!("{}.csv", prefix), to_csv(write, read))?;
+    fs::write(format!("{}.svg", prefix), to_svg(write, read))
+}
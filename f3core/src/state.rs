@@ -0,0 +1,173 @@
+// f3core/src/state.rs
+
+use std::{
+    fs,
+    io::Result,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    flow::FlowState,
+    pattern::{Pattern, PatternHint},
+    report::FileResult,
+    utils::{data_dir, read_json, write_json},
+    verify::FileStats,
+};
+
+pub const WRITE_STATE_NAME: &str = "nf3-write-state.json";
+
+/// Progress of an nf3_write run, saved after every completed file so that
+/// `--resume` can continue an interrupted run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteState {
+    pub run_id: String,
+    pub start_at: i64,
+    pub end_at: i64,
+    pub pattern: Pattern,
+    pub max_write_rate: i64,
+    /// Sectors of earlier files re-read after each file, 0 = no spot checks.
+    #[serde(default)]
+    pub spot_check: usize,
+    /// Only marker sectors are written, see `fill_markers`.
+    #[serde(default)]
+    pub quick: bool,
+    /// Last file that was completely written, `None` before the first one.
+    pub last_completed: Option<i64>,
+    pub flow: FlowState,
+    /// Time spent writing so far, over all invocations.
+    pub elapsed_ms: u64,
+}
+
+impl WriteState {
+    /// State of a new run, `run_id` is taken from its manifest once created.
+    pub fn new(start_at: i64, end_at: i64, pattern: Pattern, max_write_rate: i64) -> Self {
+        WriteState {
+            run_id: String::new(),
+            start_at,
+            end_at,
+            pattern,
+            max_write_rate,
+            spot_check: 0,
+            quick: false,
+            last_completed: None,
+            flow: FlowState::default(),
+            elapsed_ms: 0,
+        }
+    }
+
+    /// First file that still has to be written.
+    pub fn next_file(&self) -> i64 {
+        self.last_completed.map_or(self.start_at, |n| n + 1)
+    }
+
+    pub fn load(dir: &str) -> Result<Option<WriteState>> {
+        read_json(&Path::new(dir).join(WRITE_STATE_NAME))
+    }
+
+    pub fn save(&self, dir: &str) -> Result<()> {
+        write_json(&Path::new(dir).join(WRITE_STATE_NAME), self)
+    }
+
+    /// Remove the state file of `dir`, once there is nothing left to resume.
+    pub fn remove(dir: &str) -> Result<()> {
+        remove_state(&Path::new(dir).join(WRITE_STATE_NAME))
+    }
+}
+
+/// Results of an nf3_read run, saved after every verified file so that
+/// `--resume` can skip them and still print the full report.
+///
+/// It is kept off the drive, see `read_state_path`: nf3_write leaves no room
+/// for it there.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadState {
+    pub start_at: i64,
+    pub end_at: i64,
+    /// Options the files are checked with, a resume has to use the same.
+    #[serde(default)]
+    pub hint: PatternHint,
+    #[serde(default)]
+    pub quick: bool,
+    pub completed: Vec<FileResult>,
+    pub flow: FlowState,
+    /// Time spent reading so far, over all invocations.
+    pub elapsed_ms: u64,
+}
+
+impl ReadState {
+    pub fn new(start_at: i64, end_at: i64) -> Self {
+        ReadState {
+            start_at,
+            end_at,
+            hint: PatternHint::default(),
+            quick: false,
+            completed: Vec::new(),
+            flow: FlowState::default(),
+            elapsed_ms: 0,
+        }
+    }
+
+    pub fn stats_of(&self, number: i64) -> Option<&FileStats> {
+        self.completed
+            .iter()
+            .find(|f| f.number == number)
+            .map(|f| &f.stats)
+    }
+
+    pub fn record(&mut self, number: i64, stats: FileStats) {
+        self.completed.push(FileResult { number, stats });
+    }
+
+    /// Describe the options of this run, for a resume that doesn't match.
+    pub fn describe_options(&self) -> String {
+        let opt = |v: Option<String>| v.unwrap_or_else(|| "auto".to_string());
+        format!(
+            "pattern {}, seed {}, byte order {}, sector size {}, tolerance {}, quick {}",
+            opt(self.hint.kind.map(|k| k.name().to_string())),
+            self.hint.seed,
+            opt(self.hint.order.map(|o| o.name().to_string())),
+            opt(self.hint.sector_size.map(|s| s.to_string())),
+            opt(self.hint.tolerance.map(|t| t.to_string())),
+            self.quick
+        )
+    }
+
+    pub fn load(file: &Path) -> Result<Option<ReadState>> {
+        read_json(file)
+    }
+
+    pub fn save(&self, file: &Path) -> Result<()> {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_json(file, self)
+    }
+
+    /// Remove the state `file`, once the run is complete.
+    pub fn remove(file: &Path) -> Result<()> {
+        remove_state(file)
+    }
+}
+
+/// Default state file of an nf3_read run over `dev_path`, in
+/// `utils::data_dir` and named after the path, e.g.
+/// `read-state-media_USB.json` for `/media/USB/`.
+pub fn read_state_path(dev_path: &str) -> PathBuf {
+    let path = fs::canonicalize(dev_path).unwrap_or_else(|_| PathBuf::from(dev_path));
+    let key: String = path
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    data_dir().join(format!("read-state-{}.json", key.trim_matches('_')))
+}
+
+fn remove_state(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        // A read-only filesystem refuses even a missing file
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound && path.exists() => Err(e),
+        _ => Ok(()),
+    }
+}
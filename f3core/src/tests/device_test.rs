@@ -0,0 +1,82 @@
+#[cfg(test)]
+use std::{fs, path::Path};
+
+#[cfg(test)]
+use crate::device::{DeviceInfo, find_mount, parse_mountinfo};
+
+#[cfg(test)]
+const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+25 22 0:21 / /tmp rw,nosuid shared:5 - tmpfs tmpfs rw
+61 22 8:17 / /media/usb\\040stick rw,nosuid shared:30 - vfat /dev/sdb1 rw,uid=1000
+";
+
+#[cfg(test)]
+fn write(root: &Path, file: &str, content: &str) {
+    let file = root.join(file);
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(file, content).unwrap();
+}
+
+#[test]
+fn mountinfo_finds_the_longest_mount() {
+    let mounts = parse_mountinfo(MOUNTINFO);
+    assert_eq!(mounts.len(), 3);
+    assert_eq!(mounts[2].mount_point, "/media/usb stick");
+    assert_eq!(mounts[2].dev, "8:17");
+
+    let find = |p: &str| find_mount(&mounts, Path::new(p)).map(|m| m.fs_type.as_str());
+    assert_eq!(find("/media/usb stick/1.h2w"), Some("vfat"));
+    assert_eq!(find("/media/usb"), Some("ext4"));
+    assert_eq!(find("/tmp/x"), Some("tmpfs"));
+}
+
+#[cfg(unix)]
+#[test]
+fn probe_reads_a_fake_sysfs() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(root, "proc/self/mountinfo", MOUNTINFO);
+
+    let usb = "sys/devices/pci0000:00/0000:00:14.0/usb2/2-1";
+    let scsi = format!("{}/2-1:1.0/host6/target6:0:0/6:0:0:0", usb);
+    write(root, &format!("{}/idVendor", usb), "0951\n");
+    write(root, &format!("{}/serial", usb), "0019E06B07F1\n");
+    write(root, &format!("{}/vendor", scsi), "Kingston\n");
+    write(root, &format!("{}/model", scsi), "DataTraveler 3.0\n");
+    write(root, "sys/block/sdb/size", "121077760\n");
+    write(root, "sys/block/sdb/removable", "1\n");
+    fs::create_dir_all(root.join("sys/block/sdb/sdb1")).unwrap();
+    std::os::unix::fs::symlink(
+        format!("../../{}", scsi.trim_start_matches("sys/")),
+        root.join("sys/block/sdb/device"),
+    )
+    .unwrap();
+
+    let info = DeviceInfo::probe_in(root, "/media/usb stick/").unwrap();
+    assert_eq!(info.mount_point, "/media/usb stick");
+    assert_eq!(info.source, "/dev/sdb1");
+    assert_eq!(info.block_device.as_deref(), Some("sdb"));
+    assert_eq!(info.vendor.as_deref(), Some("Kingston"));
+    assert_eq!(info.model.as_deref(), Some("DataTraveler 3.0"));
+    assert_eq!(info.serial.as_deref(), Some("0019E06B07F1"));
+    assert_eq!(info.size, Some(121077760 * 512));
+    assert_eq!(info.removable, Some(true));
+    assert_eq!(info.bus.as_deref(), Some("usb"));
+    assert_eq!(
+        info.identity().as_deref(),
+        Some("Kingston DataTraveler 3.0 0019E06B07F1")
+    );
+    assert_eq!(
+        info.describe(),
+        "Kingston DataTraveler 3.0 (sdb, usb, removable)"
+    );
+
+    // No block device behind tmpfs, only the mount is known.
+    let tmp = DeviceInfo::probe_in(root, "/tmp/run").unwrap();
+    assert_eq!(
+        (tmp.fs_type.as_str(), tmp.block_device.as_deref()),
+        ("tmpfs", None)
+    );
+    assert_eq!(tmp.identity(), None);
+}
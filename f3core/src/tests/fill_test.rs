@@ -7,8 +7,12 @@ use tempfile::tempfile;
 
 #[cfg(test)]
 use crate::{
-    file_fill::{fill_buffer, write_chunk},
+    file_fill::{MARKER_SPACING, fill_buffer, write_chunk},
     flow::DynamicBuffer,
+    pattern::{GeneratorKind, Pattern, PatternHint},
+    tests::helpers::{gen_seeded_sector, lcg},
+    utils::GIB,
+    verify::{FileStats, check_buffer, detect_pattern},
 };
 #[cfg(test)]
 const SECTOR_SIZE: usize = 512;
@@ -18,12 +22,81 @@ fn test_fill_buffer_sector() {
     let mut buf = vec![0u8; SECTOR_SIZE];
     let len: usize = buf.len();
     let off0: u64 = 1234;
-    let off1 = fill_buffer(&mut buf[..], len, off0);
+    let off1 = fill_buffer(&mut buf[..], len, off0, &Pattern::default());
     assert_eq!(off1, off0 + SECTOR_SIZE as u64);
-    let first = u64::from_ne_bytes(buf[0..8].try_into().unwrap());
+    let first = u64::from_le_bytes(buf[0..8].try_into().unwrap());
     assert_eq!(first, off0);
 }
 
+#[test]
+fn test_fill_buffer_seeded_matches_unseeded_layout() {
+    let mut plain = vec![0u8; SECTOR_SIZE];
+    let mut seeded = vec![0u8; SECTOR_SIZE];
+    fill_buffer(&mut plain[..], SECTOR_SIZE, 4096, &lcg(0));
+    fill_buffer(&mut seeded[..], SECTOR_SIZE, 4096, &lcg(42));
+
+    // Same header, seed in the second word, different payload
+    assert_eq!(plain[0..8], seeded[0..8]);
+    assert_eq!(u64::from_le_bytes(seeded[8..16].try_into().unwrap()), 42);
+    assert_ne!(plain[16..], seeded[16..]);
+}
+
+#[test]
+fn test_detect_pattern() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("3.h2w");
+    let chacha = Pattern::new(GeneratorKind::Chacha, 42);
+    let mut buf = vec![0u8; 4 * SECTOR_SIZE];
+    fill_buffer(&mut buf, 4 * SECTOR_SIZE, 3 * GIB, &chacha);
+    std::fs::write(&path, &buf).unwrap();
+
+    let path = path.to_str().unwrap();
+    let hint = |kind, seed| PatternHint {
+        kind,
+        seed,
+        ..Default::default()
+    };
+    assert_eq!(detect_pattern(path, &hint(None, 42)), Some(chacha));
+    assert_eq!(
+        detect_pattern(path, &hint(Some(GeneratorKind::Lcg), 42)),
+        None
+    );
+    assert_eq!(detect_pattern(path, &hint(None, 7)), None);
+}
+
+#[test]
+fn test_detect_pattern_past_a_bad_first_sector() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("3.h2w");
+    let chacha = Pattern::new(GeneratorKind::Chacha, 42);
+    let len = 2 * MARKER_SPACING as usize;
+    let mut buf = vec![0u8; len];
+    fill_buffer(&mut buf, len, 3 * GIB, &chacha);
+    // Garbage at the start of the first MiB
+    buf[..SECTOR_SIZE].fill(0x5A);
+    std::fs::write(&path, &buf).unwrap();
+
+    let hint = PatternHint {
+        seed: 42,
+        ..Default::default()
+    };
+    assert_eq!(detect_pattern(path.to_str().unwrap(), &hint), Some(chacha));
+}
+
+#[test]
+fn test_detect_pattern_finds_the_stored_seed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("2.h2w");
+    let buf: Vec<u8> = (0..8)
+        .flat_map(|i| gen_seeded_sector(2 * GIB + i * SECTOR_SIZE as u64, 77))
+        .collect();
+    std::fs::write(&path, &buf).unwrap();
+
+    // Neither a manifest nor --seed
+    let found = detect_pattern(path.to_str().unwrap(), &PatternHint::default());
+    assert_eq!(found, Some(lcg(77)));
+}
+
 #[test]
 fn test_write_chunk_and_read_back() {
     let mut dbuf = DynamicBuffer::new();
@@ -32,13 +105,47 @@ fn test_write_chunk_and_read_back() {
     let sector_size = SECTOR_SIZE;
 
     // Writing to one sector
-    write_chunk(&mut dbuf, &mut file, sector_size, &mut offset).unwrap();
+    write_chunk(
+        &mut dbuf,
+        &mut file,
+        sector_size,
+        &mut offset,
+        &Pattern::default(),
+    )
+    .unwrap();
     assert_eq!(offset, SECTOR_SIZE as u64);
 
     // REad it and compare
     file.seek(SeekFrom::Start(0)).unwrap();
     let mut data = vec![0u8; SECTOR_SIZE];
     file.read_exact(&mut data).unwrap();
-    let first = u64::from_ne_bytes(data[0..8].try_into().unwrap());
+    let first = u64::from_le_bytes(data[0..8].try_into().unwrap());
     assert_eq!(first, 0);
 }
+
+#[test]
+fn large_sectors_and_strict_tolerance() {
+    let pattern = Pattern::default().with_sector_size(4096);
+    let mut buf = vec![0u8; 2 * 4096];
+    let end = fill_buffer(&mut buf, 2 * 4096, GIB, &pattern);
+    assert_eq!(end, GIB + 2 * 4096);
+    // Header of the second sector
+    assert_eq!(
+        u64::from_le_bytes(buf[4096..4104].try_into().unwrap()),
+        GIB + 4096
+    );
+
+    let mut stats = FileStats::new();
+    check_buffer(&buf, buf.len(), GIB, &pattern, &mut stats);
+    assert_eq!(stats.secs_ok(), 2);
+
+    // One word off: changed by default, corrupted with tolerance 0
+    buf[4096 + 100] ^= 1;
+    let mut stats = FileStats::new();
+    check_buffer(&buf, buf.len(), GIB, &pattern, &mut stats);
+    assert_eq!(stats.secs_changed(), 1);
+
+    let mut stats = FileStats::new();
+    check_buffer(&buf, buf.len(), GIB, &pattern.with_tolerance(0), &mut stats);
+    assert_eq!(stats.secs_corrupted(), 1);
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+use crate::{
+    device::DeviceInfo,
+    guard::{self, Hazard, MAX_FOREIGN_FILES},
+};
+
+#[cfg(test)]
+fn device(mount_point: &str, fs_type: &str, removable: bool, bus: &str) -> DeviceInfo {
+    DeviceInfo {
+        mount_point: mount_point.into(),
+        fs_type: fs_type.into(),
+        source: "/dev/sda1".into(),
+        block_device: Some("sda".into()),
+        removable: Some(removable),
+        bus: Some(bus.into()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn guard_flags_dangerous_targets() {
+    let stick = device("/media/usb", "vfat", true, "usb");
+    assert!(guard::hazards(Some(&stick), 0).is_empty());
+    // USB disks often claim not to be removable.
+    let usb_ssd = device("/media/ssd", "exfat", false, "usb");
+    assert!(guard::hazards(Some(&usb_ssd), MAX_FOREIGN_FILES).is_empty());
+
+    let root = device("/", "ext4", false, "nvme");
+    assert_eq!(
+        guard::hazards(Some(&root), 0),
+        [
+            Hazard::RootFs,
+            Hazard::FixedDisk("/dev/sda1 (sda, nvme, fixed)".into())
+        ]
+    );
+    let nfs = DeviceInfo {
+        mount_point: "/mnt/share".into(),
+        fs_type: "nfs4".into(),
+        ..Default::default()
+    };
+    assert_eq!(
+        guard::hazards(Some(&nfs), MAX_FOREIGN_FILES + 1),
+        [
+            Hazard::NetworkFs("nfs4".into()),
+            Hazard::ForeignFiles(MAX_FOREIGN_FILES + 1)
+        ]
+    );
+}
+
+#[test]
+fn guard_fails_closed_without_a_device() {
+    assert_eq!(guard::hazards(None, 0), [Hazard::UnknownDevice]);
+    assert_eq!(
+        guard::hazards(None, MAX_FOREIGN_FILES + 1),
+        [
+            Hazard::UnknownDevice,
+            Hazard::ForeignFiles(MAX_FOREIGN_FILES + 1)
+        ]
+    );
+}
+
+#[test]
+fn foreign_files_skip_nf3_files() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in [
+        "1.h2w",
+        "12.h2w",
+        "nf3-manifest.json",
+        ".nf3.lock",
+        "notes.txt",
+        "x.h2w",
+    ] {
+        std::fs::write(dir.path().join(name), b"").unwrap();
+    }
+    std::fs::create_dir(dir.path().join("photos")).unwrap();
+    assert_eq!(guard::count_foreign_files(dir.path().to_str().unwrap()), 3);
+}
@@ -1,14 +1,16 @@
 use crate::{
-    utils::{SECTOR_SIZE, random_number},
-    verify::FileStats,
+    file_fill::fill_buffer,
+    pattern::{GeneratorKind, Pattern},
+    utils::{GIB, SECTOR_SIZE, random_number},
+    verify::{FileStats, check_buffer},
 };
 
 // ---- helpers ----
 
 #[inline(always)]
-pub fn write_word_ne(buf: &mut [u8], i: usize, val: u64) {
+pub fn write_word_le(buf: &mut [u8], i: usize, val: u64) {
     let start = i * 8;
-    buf[start..start + 8].copy_from_slice(&val.to_ne_bytes());
+    buf[start..start + 8].copy_from_slice(&val.to_le_bytes());
 }
 
 pub fn gen_ok_sector(expected_offset: u64) -> [u8; SECTOR_SIZE] {
@@ -18,17 +20,39 @@ pub fn gen_ok_sector(expected_offset: u64) -> [u8; SECTOR_SIZE] {
     let mut sector = [0u8; SECTOR_SIZE];
 
     // word[0] = expected_offset
-    write_word_ne(&mut sector, 0, expected_offset);
+    write_word_le(&mut sector, 0, expected_offset);
 
     // words[1..] = random_number(chain)
     let mut rn = expected_offset;
     for i in 1..num_words {
         rn = random_number(rn);
-        write_word_ne(&mut sector, i, rn);
+        write_word_le(&mut sector, i, rn);
     }
     sector
 }
 
+/// Same as `gen_ok_sector`, but for a run with a non-zero `seed`:
+/// word[1] holds the seed and the chain starts from `expected_offset ^ seed`.
+pub fn gen_seeded_sector(expected_offset: u64, seed: u64) -> [u8; SECTOR_SIZE] {
+    let num_words = SECTOR_SIZE / size_of::<u64>();
+    let mut sector = [0u8; SECTOR_SIZE];
+
+    write_word_le(&mut sector, 0, expected_offset);
+    write_word_le(&mut sector, 1, seed);
+
+    let mut rn = random_number(expected_offset ^ seed);
+    for i in 2..num_words {
+        rn = random_number(rn);
+        write_word_le(&mut sector, i, rn);
+    }
+    sector
+}
+
+/// Shorthand for an LCG pattern with `seed`.
+pub fn lcg(seed: u64) -> Pattern {
+    Pattern::new(GeneratorKind::Lcg, seed)
+}
+
 /// Mutates N 64-bit words (with indices >= 1) to force a desired class.
 /// Does not touch the header word at index 0.
 pub fn bump_words(sector: &mut [u8], word_indices: &[usize]) {
@@ -36,8 +60,8 @@ pub fn bump_words(sector: &mut [u8], word_indices: &[usize]) {
         let start = i * 8;
         let mut arr = [0u8; 8];
         arr.copy_from_slice(&sector[start..start + 8]);
-        let v = u64::from_ne_bytes(arr) + 1;
-        sector[start..start + 8].copy_from_slice(&v.to_ne_bytes());
+        let v = u64::from_le_bytes(arr) + 1;
+        sector[start..start + 8].copy_from_slice(&v.to_le_bytes());
     }
 }
 
@@ -47,3 +71,29 @@ pub fn assert_counts(s: &FileStats, ok: u64, corrupted: u64, changed: u64, overw
     assert_eq!(s.secs_changed(), changed, "secs_changed");
     assert_eq!(s.secs_overwritten(), overwritten, "secs_overwritten");
 }
+
+/// Write `<dir><number>.h2w` with `sectors` sectors of `pattern`.
+pub fn write_h2w(dir: &str, number: i64, sectors: usize, pattern: &Pattern) {
+    let mut buf = vec![0u8; sectors * SECTOR_SIZE];
+    fill_buffer(
+        &mut buf,
+        sectors * SECTOR_SIZE,
+        number as u64 * GIB,
+        pattern,
+    );
+    std::fs::write(format!("{}{}.h2w", dir, number), buf).unwrap();
+}
+
+/// Stats of two sectors read back fine, and of the same two sectors with
+/// the second one overwritten by `fill` bytes.
+pub fn good_and_bad_stats(fill: u8) -> (FileStats, FileStats) {
+    let pattern = Pattern::default();
+    let mut buf = vec![0u8; 2 * SECTOR_SIZE];
+    fill_buffer(&mut buf, 2 * SECTOR_SIZE, 0, &pattern);
+    let mut good = FileStats::new();
+    check_buffer(&buf, buf.len(), 0, &pattern, &mut good);
+    buf[SECTOR_SIZE..].fill(fill);
+    let mut bad = FileStats::new();
+    check_buffer(&buf, buf.len(), 0, &pattern, &mut bad);
+    (good, bad)
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+use crate::{
+    device::DeviceInfo,
+    history::{self, HistoryEntry},
+    report::{ReadSummary, RunReport, Verdict, WriteSummary},
+};
+
+#[cfg(test)]
+fn entry(started_at: u64, write_speed: f64, read_speed: f64) -> HistoryEntry {
+    let mut report = RunReport::new("/mnt/usb/");
+    report.started_at = started_at;
+    report.write = Some(WriteSummary {
+        avg_speed: write_speed,
+        ..Default::default()
+    });
+    report.read = Some(ReadSummary {
+        avg_speed: read_speed,
+        ..Default::default()
+    });
+    HistoryEntry::new("write", &report)
+}
+
+#[test]
+fn history_appends_and_loads_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("nf3").join(history::HISTORY_NAME);
+
+    assert!(history::load(&file).unwrap().is_empty());
+    history::append(&file, &entry(200, 20e6, 40e6)).unwrap();
+    history::append(&file, &entry(100, 25e6, 50e6)).unwrap();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&file)
+        .and_then(|mut f| std::io::Write::write_all(&mut f, b"not json\n"))
+        .unwrap();
+
+    let entries = history::load(&file).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].started_at, 100);
+    assert_eq!(entries[0].verdict, Verdict::Ok);
+
+    let devices = history::by_device(&entries);
+    assert_eq!(devices["/mnt/usb/"].len(), 2);
+    assert_eq!(
+        history::trend(&devices["/mnt/usb/"]),
+        ["write speed -20.0%", "read speed -20.0%"]
+    );
+}
+
+#[test]
+fn history_groups_by_serial_when_known() {
+    let mut report = RunReport::new("/mnt/usb/");
+    assert_eq!(HistoryEntry::new("read", &report).device, "/mnt/usb/");
+
+    report.device = Some(DeviceInfo {
+        vendor: Some("Kingston".into()),
+        model: Some("DataTraveler".into()),
+        serial: Some("0123".into()),
+        ..Default::default()
+    });
+    assert_eq!(
+        HistoryEntry::new("read", &report).device,
+        "Kingston DataTraveler 0123"
+    );
+}
+
+#[test]
+fn utc_time_formats_dates() {
+    assert_eq!(history::utc_time(0), "1970-01-01 00:00");
+    assert_eq!(history::utc_time(951_782_400 + 3_660), "2000-02-29 01:01");
+    assert_eq!(history::utc_time(1_792_352_275), "2026-10-18 19:37");
+}
@@ -0,0 +1,41 @@
+#[cfg(test)]
+use std::io::ErrorKind;
+
+#[cfg(test)]
+use crate::lock::{LOCK_NAME, RunLock};
+
+#[cfg(unix)]
+#[test]
+fn second_run_is_refused_until_the_first_ends() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let first = RunLock::acquire(path, "nf3_write", false).unwrap();
+    let err = RunLock::acquire(path, "nf3_read", false).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert!(err.to_string().contains("nf3_write (pid "));
+
+    drop(first);
+    let _second = RunLock::acquire(path, "nf3_read", false).unwrap();
+    let holder = std::fs::read_to_string(dir.path().join(LOCK_NAME)).unwrap();
+    assert!(holder.starts_with("nf3_read (pid "));
+}
+
+#[cfg(unix)]
+#[test]
+fn read_only_lock_still_excludes_a_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    // No lock file yet: the directory itself is locked
+    let reader = RunLock::acquire_without_writing(path, false).unwrap();
+    assert!(!dir.path().join(LOCK_NAME).exists());
+    drop(reader);
+
+    let writer = RunLock::acquire(path, "nf3_write", false).unwrap();
+    let err = RunLock::acquire_without_writing(path, false).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert!(err.to_string().contains("nf3_write (pid "));
+    drop(writer);
+    let _reader = RunLock::acquire_without_writing(path, false).unwrap();
+}
@@ -0,0 +1,154 @@
+#[cfg(test)]
+use crate::{
+    manifest::{Manifest, ManifestIssue},
+    pattern::{ByteOrder, GeneratorKind, Pattern, PatternHint},
+    speed::SpeedSample,
+    utils::GIB,
+};
+
+#[cfg(test)]
+fn touch(dir: &std::path::Path, number: i64, size: usize) {
+    std::fs::write(dir.join(format!("{}.h2w", number)), vec![0u8; size]).unwrap();
+}
+
+#[test]
+fn manifest_roundtrip() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = dir.path().to_str().unwrap();
+
+    let mut manifest = Manifest::new(&Pattern::new(GeneratorKind::Chacha, 99));
+    manifest.record_file(2, 512);
+    manifest.record_file(1, 1024);
+    touch(dir.path(), 1, 1024);
+    touch(dir.path(), 2, 512);
+    manifest.update_sizes(p);
+    manifest.save(p).unwrap();
+
+    let loaded = Manifest::load(p).unwrap().unwrap();
+    assert_eq!(loaded, manifest);
+    assert_eq!(loaded.files[0].number, 1);
+    assert_eq!(loaded.pattern(), Pattern::new(GeneratorKind::Chacha, 99));
+}
+
+#[test]
+fn manifest_absent_is_none() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(
+        Manifest::load(dir.path().to_str().unwrap())
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn manifest_check_reports_missing_extra_and_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = dir.path().to_str().unwrap();
+
+    let mut manifest = Manifest::new(&Pattern::default());
+    for n in 1..=3 {
+        manifest.record_file(n, 512);
+    }
+    touch(dir.path(), 1, 512);
+    touch(dir.path(), 3, 1024);
+    touch(dir.path(), 4, 512);
+
+    let issues = manifest.check(p, &[1, 3, 4], 1, 0);
+    assert_eq!(
+        issues,
+        vec![
+            ManifestIssue::Missing(2),
+            ManifestIssue::SizeMismatch {
+                number: 3,
+                expected: 512,
+                actual: 1024
+            },
+            ManifestIssue::Extra(4),
+        ]
+    );
+
+    // Single file mode only looks at its own number
+    assert!(manifest.check(p, &[1], 1, 1).is_empty());
+}
+
+#[test]
+fn manifest_check_ignores_files_outside_the_written_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = dir.path().to_str().unwrap();
+
+    // `--start-at 3` over files 1 and 2 of an earlier run
+    let mut manifest = Manifest::new(&Pattern::default());
+    (manifest.start_at, manifest.end_at) = (3, 4);
+    for n in 1..=4 {
+        touch(dir.path(), n, 512);
+    }
+    for n in 3..=4 {
+        manifest.record_file(n, 512);
+    }
+
+    assert!(manifest.check(p, &[1, 2, 3, 4], 1, 0).is_empty());
+    touch(dir.path(), 5, 512);
+    assert!(manifest.check(p, &[1, 2, 3, 4, 5], 1, 0).is_empty());
+}
+
+#[test]
+fn manifest_fills_only_unset_hint_fields() {
+    let manifest = Manifest::new(&Pattern::new(GeneratorKind::Chacha, 99));
+
+    let mut hint = PatternHint::default();
+    manifest.apply_to(&mut hint);
+    assert_eq!(hint.kind, Some(GeneratorKind::Chacha));
+    assert_eq!(hint.order, Some(ByteOrder::Little));
+    assert_eq!(hint.seed, 99);
+
+    let mut hint = PatternHint {
+        kind: Some(GeneratorKind::Lcg),
+        seed: 5,
+        tolerance: Some(0),
+        ..Default::default()
+    };
+    manifest.apply_to(&mut hint);
+    assert_eq!(hint.kind, Some(GeneratorKind::Lcg));
+    assert_eq!(hint.seed, 5);
+    assert_eq!(hint.sector_size, Some(512));
+    assert_eq!(hint.tolerance, Some(0));
+}
+
+#[test]
+fn manifest_records_sector_size_and_tolerance() {
+    let pattern = Pattern::new(GeneratorKind::Lcg, 1)
+        .with_sector_size(4096)
+        .with_tolerance(0);
+    let manifest = Manifest::new(&pattern);
+    assert_eq!((manifest.sector_size, manifest.tolerance), (4096, 0));
+    assert_eq!(manifest.pattern(), pattern);
+
+    let mut hint = PatternHint::default();
+    manifest.apply_to(&mut hint);
+    assert_eq!(hint.fallback(), pattern);
+}
+
+#[test]
+fn manifest_gives_the_write_side_of_the_report() {
+    let mut manifest = Manifest::new(&Pattern::default());
+    manifest.record_file(1, GIB);
+    manifest.record_file(2, GIB / 2);
+    // 1 GiB in 10 s, then half a GiB in 10 s
+    manifest.write_speeds = vec![
+        SpeedSample {
+            offset: GIB,
+            speed: GIB as f64 / 10.0,
+        },
+        SpeedSample {
+            offset: GIB + GIB / 2,
+            speed: GIB as f64 / 20.0,
+        },
+    ];
+
+    let write = manifest.write_summary();
+    assert_eq!(write.last_completed, Some(1));
+    assert_eq!(write.bytes_written, GIB + GIB / 2);
+    assert_eq!(write.elapsed_ms, 20_000);
+    assert_eq!(write.avg_speed, (GIB + GIB / 2) as f64 / 20.0);
+    assert_eq!(write.speeds, manifest.write_speeds);
+}
@@ -1,3 +1,16 @@
 // f3core/src/tests/mod.rs
+mod device_test;
 mod fill_test;
+mod guard_test;
 pub mod helpers;
+mod history_test;
+mod lock_test;
+mod manifest_test;
+mod pattern_test;
+mod probe_test;
+mod report_test;
+mod speed_test;
+mod spot_check_test;
+mod state_test;
+#[cfg(all(feature = "tui", unix))]
+mod tui_test;
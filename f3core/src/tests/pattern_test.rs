@@ -0,0 +1,105 @@
+#[cfg(test)]
+use crate::{
+    pattern::{ByteOrder, GeneratorKind, Pattern},
+    utils::{GIB, SECTOR_SIZE},
+    verify::FileStats,
+};
+
+#[cfg(test)]
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+fn sector0(pattern: &Pattern, number: u64) -> [u8; SECTOR_SIZE] {
+    let mut sector = [0u8; SECTOR_SIZE];
+    pattern.fill_sector(&mut sector, number * GIB);
+    sector
+}
+
+// Sector 0 of `<number>.h2w`: the first three words and an FNV-1a hash of the
+// whole sector. These lock the on-disk format, do not update them lightly.
+#[cfg(test)]
+const LCG_VECTORS: [(u64, [u8; 24], u64); 3] = [
+    (
+        1,
+        [
+            0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0xc0, 0x03, 0x00,
+            0x00, 0x40, 0x10, 0x01, 0x00, 0x40, 0x49, 0x00, 0x00, 0x80,
+        ],
+        0xb93e_4d2f_afe7_0f98,
+    ),
+    (
+        2,
+        [
+            0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x80, 0x07, 0x00,
+            0x00, 0x80, 0x10, 0x01, 0x00, 0x80, 0x81, 0x00, 0x00, 0x00,
+        ],
+        0x79ad_1a09_8eba_94ce,
+    ),
+    (
+        7,
+        [
+            0x00, 0x00, 0x00, 0xc0, 0x01, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x40, 0x1a, 0x00,
+            0x00, 0xc0, 0x10, 0x01, 0x00, 0xc0, 0x9a, 0x01, 0x00, 0x80,
+        ],
+        0xd042_571d_6932_822f,
+    ),
+];
+
+#[test]
+fn lcg_sector0_layout_is_little_endian() {
+    for (number, head, hash) in LCG_VECTORS {
+        let sector = sector0(&Pattern::default(), number);
+        assert_eq!(sector[..24], head, "file {}", number);
+        assert_eq!(fnv1a(&sector), hash, "file {}", number);
+    }
+}
+
+#[test]
+fn seeded_sector0_layout() {
+    let seeded = sector0(&Pattern::new(GeneratorKind::Lcg, 0x0102_0304_0506_0708), 1);
+    assert_eq!(seeded[..8], LCG_VECTORS[0].1[..8]);
+    assert_eq!(seeded[8..16], [8, 7, 6, 5, 4, 3, 2, 1]);
+
+    let chacha = sector0(
+        &Pattern::new(GeneratorKind::Chacha, 0x0102_0304_0506_0708),
+        1,
+    );
+    assert_eq!(chacha[..16], seeded[..16]);
+    assert_eq!(fnv1a(&chacha), 0x298d_f8ee_5957_944d);
+}
+
+#[test]
+fn big_endian_is_word_swapped_little_endian() {
+    for (number, head, _) in LCG_VECTORS {
+        let sector = sector0(&Pattern::default().with_order(ByteOrder::Big), number);
+        for (word, le_word) in sector[..24].chunks_exact(8).zip(head.chunks_exact(8)) {
+            let mut swapped = le_word.to_vec();
+            swapped.reverse();
+            assert_eq!(word, &swapped[..]);
+        }
+    }
+}
+
+#[test]
+fn big_endian_files_verify_only_as_big_endian() {
+    let big = Pattern::default().with_order(ByteOrder::Big);
+    let sector = sector0(&big, 3);
+
+    let mut stats = FileStats::new();
+    crate::verify::check_buffer(&sector, SECTOR_SIZE, 3 * GIB, &big, &mut stats);
+    assert_eq!(stats.secs_ok(), 1);
+
+    let mut stats = FileStats::new();
+    crate::verify::check_buffer(
+        &sector,
+        SECTOR_SIZE,
+        3 * GIB,
+        &Pattern::default(),
+        &mut stats,
+    );
+    assert_eq!(stats.secs_ok(), 0);
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+use std::{
+    collections::VecDeque,
+    io::{Read, Result, Seek, SeekFrom, Write},
+};
+
+#[cfg(test)]
+use crate::{
+    probe::{CacheSize, ProbeTarget, probe_cache_size},
+    utils::SECTOR_SIZE,
+};
+
+/// Fake drive: writes land in a FIFO cache of `cache_sectors` sectors,
+/// evicted sectors survive only below `real_size`, everything else reads as zeros.
+#[cfg(test)]
+struct CachedDevice {
+    storage: Vec<u8>,
+    cache: VecDeque<(u64, Vec<u8>)>,
+    cache_sectors: usize,
+    pos: u64,
+}
+
+#[cfg(test)]
+impl CachedDevice {
+    fn new(real_size: usize, cache_size: usize) -> Self {
+        Self {
+            storage: vec![0u8; real_size],
+            cache: VecDeque::new(),
+            cache_sectors: cache_size / SECTOR_SIZE,
+            pos: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Write for CachedDevice {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        for sector in buf.chunks_exact(SECTOR_SIZE) {
+            self.cache.retain(|(off, _)| *off != self.pos);
+            self.cache.push_back((self.pos, sector.to_vec()));
+            if self.cache.len() > self.cache_sectors {
+                let (off, data) = self.cache.pop_front().unwrap();
+                let off = off as usize;
+                if off + SECTOR_SIZE <= self.storage.len() {
+                    self.storage[off..off + SECTOR_SIZE].copy_from_slice(&data);
+                }
+            }
+            self.pos += SECTOR_SIZE as u64;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl Read for CachedDevice {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        for sector in buf.chunks_exact_mut(SECTOR_SIZE) {
+            let off = self.pos as usize;
+            if let Some((_, data)) = self.cache.iter().find(|(o, _)| *o == self.pos) {
+                sector.copy_from_slice(data);
+            } else if off + SECTOR_SIZE <= self.storage.len() {
+                sector.copy_from_slice(&self.storage[off..off + SECTOR_SIZE]);
+            } else {
+                sector.fill(0);
+            }
+            self.pos += SECTOR_SIZE as u64;
+        }
+        Ok(buf.len())
+    }
+}
+
+#[cfg(test)]
+impl Seek for CachedDevice {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        if let SeekFrom::Start(p) = pos {
+            self.pos = p;
+        }
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+impl ProbeTarget for CachedDevice {
+    fn drop_cache(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+const BLOCK: usize = 16 * SECTOR_SIZE;
+
+#[test]
+fn probe_detects_cache_without_storage() {
+    let mut dev = CachedDevice::new(0, 4 * BLOCK);
+    let res = probe_cache_size(&mut dev, BLOCK, 64 * BLOCK as u64).unwrap();
+    assert_eq!(
+        res,
+        CacheSize::Detected {
+            bytes: 4 * BLOCK as u64
+        }
+    );
+}
+
+#[test]
+fn probe_no_cache_loss_on_real_storage() {
+    let mut dev = CachedDevice::new(64 * BLOCK, 4 * BLOCK);
+    let res = probe_cache_size(&mut dev, BLOCK, 16 * BLOCK as u64).unwrap();
+    assert_eq!(
+        res,
+        CacheSize::NotDetected {
+            tested: 16 * BLOCK as u64
+        }
+    );
+}
@@ -0,0 +1,149 @@
+#[cfg(test)]
+use crate::{
+    file_fill::fill_buffer,
+    junit,
+    pattern::Pattern,
+    report::{FileResult, ReadSummary, RunReport, WriteSummary},
+    speed::SpeedSample,
+    tests::helpers::good_and_bad_stats,
+    utils::SECTOR_SIZE,
+    verify::{FileClass, FileStats, check_buffer},
+};
+
+#[test]
+fn run_report_roundtrip() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("report.json");
+    let file = file.to_str().unwrap();
+
+    let mut report = RunReport::new("/mnt/usb/");
+    report.run_id = Some("00000000deadbeef".to_string());
+    report.interrupted = true;
+    report.write = Some(WriteSummary {
+        start_at: 1,
+        end_at: 8,
+        last_completed: Some(3),
+        bytes_written: 3 << 30,
+        avg_speed: 12.5e6,
+        elapsed_ms: 1234,
+        fake_capacity_at: None,
+        write_error: false,
+        quick: false,
+        sector_size: SECTOR_SIZE,
+        speeds: vec![SpeedSample {
+            offset: 1 << 30,
+            speed: 12.5e6,
+        }],
+    });
+    report.read = Some(ReadSummary {
+        files: vec![FileResult {
+            number: 1,
+            stats: FileStats::new(),
+        }],
+        missing: vec![2],
+        ..Default::default()
+    });
+    report.save(file).unwrap();
+
+    assert_eq!(RunReport::load(file).unwrap(), report);
+}
+
+#[test]
+fn file_stats_add_sums_counters() {
+    let pattern = Pattern::default();
+    let mut buf = vec![0u8; 4 * SECTOR_SIZE];
+    fill_buffer(&mut buf, 4 * SECTOR_SIZE, 0, &pattern);
+    buf[2 * SECTOR_SIZE..].fill(0xff);
+
+    let mut stats = FileStats::new();
+    check_buffer(&buf, buf.len(), 0, &pattern, &mut stats);
+    assert_eq!((stats.secs_ok(), stats.secs_lost()), (2, 2));
+
+    let mut totals = FileStats::new();
+    totals.add(&stats);
+    totals.add(&stats);
+    assert_eq!((totals.secs_ok(), totals.secs_lost()), (4, 4));
+    assert_eq!(totals.bytes_read(), 2 * stats.bytes_read());
+}
+
+#[test]
+fn html_report_shows_verdict_and_bad_ranges() {
+    let (good, bad) = good_and_bad_stats(0x5a);
+
+    let mut report = RunReport::new("/mnt/<usb>/");
+    report.read = Some(ReadSummary {
+        files: [(1, &good), (3, &bad), (4, &bad), (6, &good)]
+            .into_iter()
+            .map(|(number, stats)| FileResult {
+                number,
+                stats: stats.clone(),
+            })
+            .collect(),
+        missing: vec![5],
+        ..Default::default()
+    });
+    report.read.as_mut().unwrap().totals.add(&bad);
+    report.started_at = 1_700_000_000;
+
+    let html = report.to_html();
+    assert!(html.contains("<th>Started (UTC)</th><td>2023-11-14 22:13</td>"));
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("FAILED: the drive lost data"));
+    assert!(html.contains("/mnt/&lt;usb&gt;/"));
+    assert!(html.contains("<li>3.h2w - 5.h2w (GiB 2 to 5)</li>"));
+    assert!(!html.contains("<svg"));
+
+    report.read = None;
+    assert!(report.to_html().contains("NOT VERIFIED"));
+}
+
+#[test]
+fn junit_has_a_case_per_file() {
+    let (good, bad) = good_and_bad_stats(0);
+
+    let mut report = RunReport::new("/mnt/usb/");
+    report.read = Some(ReadSummary {
+        files: vec![
+            FileResult {
+                number: 1,
+                stats: good,
+            },
+            FileResult {
+                number: 2,
+                stats: bad,
+            },
+        ],
+        missing: vec![3],
+        elapsed_ms: 1500,
+        ..Default::default()
+    });
+
+    let xml = junit::render(&report);
+    assert!(xml.contains("<testsuites tests=\"3\" failures=\"2\" errors=\"0\" time=\"1.500\">"));
+    assert!(xml.contains("name=\"1.h2w\"/>"));
+    assert!(xml.contains(
+        "message=\"1 of 2 sectors not ok: 0 corrupted, 0 changed, 0 overwritten, 0 stale, 1 zeroed, 0 ones\""
+    ));
+    assert!(xml.contains("type=\"Missing\" message=\"3.h2w is missing\""));
+}
+
+#[test]
+fn junit_fails_when_nothing_was_checked() {
+    let mut report = RunReport::new("/mnt/usb/");
+    let xml = junit::render(&report);
+    assert!(xml.contains("tests=\"1\" failures=\"0\" errors=\"1\""));
+    assert!(xml.contains("<error type=\"ReadError\""));
+
+    report.read = Some(ReadSummary::default());
+    let xml = junit::render(&report);
+    assert!(xml.contains("tests=\"1\" failures=\"1\" errors=\"0\""));
+    assert!(xml.contains("<failure type=\"NoFiles\""));
+}
+
+#[test]
+fn file_class_follows_most_of_the_lost_sectors() {
+    let (good, bad) = good_and_bad_stats(0);
+    assert_eq!(good.class(), FileClass::Ok);
+    // Its one lost sector is zero-filled
+    assert_eq!(bad.class(), FileClass::Erased);
+}
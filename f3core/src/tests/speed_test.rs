@@ -0,0 +1,59 @@
+#[cfg(test)]
+use crate::{
+    speed::{MAX_SAMPLES, SpeedLog, SpeedSample, to_csv, to_svg},
+    utils::GIB,
+};
+
+#[test]
+fn speed_log_halves_resolution_when_full() {
+    let mut log = SpeedLog::default();
+    for i in 0..=MAX_SAMPLES as u64 {
+        log.push((i + 1) * 1000, 10.0);
+    }
+    log.push(0, f64::INFINITY);
+
+    assert_eq!(log.samples().len(), MAX_SAMPLES / 2 + 1);
+    // The extent is kept, only coarser.
+    assert_eq!(
+        log.samples().last().unwrap().offset,
+        (MAX_SAMPLES as u64 + 1) * 1000
+    );
+    assert!(log.samples().iter().all(|s| s.speed == 10.0));
+}
+
+#[test]
+fn csv_and_svg_cover_both_phases() {
+    let write = [
+        SpeedSample {
+            offset: GIB,
+            speed: 80e6,
+        },
+        SpeedSample {
+            offset: 2 * GIB,
+            speed: 20e6,
+        },
+    ];
+    let read = [SpeedSample {
+        offset: 2 * GIB,
+        speed: 100e6,
+    }];
+
+    let csv = to_csv(&write, &read);
+    assert_eq!(
+        csv.lines().collect::<Vec<_>>(),
+        [
+            "phase,offset_bytes,speed_bytes_per_s",
+            "write,1073741824,80000000",
+            "write,2147483648,20000000",
+            "read,2147483648,100000000",
+        ]
+    );
+
+    let svg = to_svg(&write, &read);
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<polyline").count(), 2);
+
+    // Without samples there is still a valid, empty chart.
+    assert_eq!(to_svg(&[], &[]).matches("<polyline").count(), 0);
+}
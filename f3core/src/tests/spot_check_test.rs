@@ -0,0 +1,49 @@
+#[cfg(test)]
+use crate::{
+    file_fill::fill_buffer,
+    pattern::{GeneratorKind, Pattern},
+    tests::helpers::write_h2w,
+    utils::{GIB, SECTOR_SIZE},
+    verify::spot_check,
+};
+
+#[test]
+fn spot_check_passes_good_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    let pattern = Pattern::new(GeneratorKind::Chacha, 9);
+    write_h2w(&p, 1, 16, &pattern);
+    write_h2w(&p, 2, 16, &pattern);
+
+    let mut rng = 1;
+    let stats = spot_check(&p, &[1, 2], 32, SECTOR_SIZE as u64, &pattern, &mut rng).unwrap();
+    assert_eq!(stats.secs_ok(), 32);
+    assert_eq!(stats.bytes_read(), 32 * SECTOR_SIZE as u64);
+
+    assert_eq!(
+        spot_check(&p, &[], 32, SECTOR_SIZE as u64, &pattern, &mut rng)
+            .unwrap()
+            .secs_ok(),
+        0
+    );
+}
+
+#[test]
+fn spot_check_finds_wrapped_around_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    let pattern = Pattern::default();
+    write_h2w(&p, 1, 16, &pattern);
+
+    // A fake drive maps 2.h2w onto the blocks of 1.h2w
+    let mut buf = vec![0u8; 16 * SECTOR_SIZE];
+    fill_buffer(&mut buf, 16 * SECTOR_SIZE, 2 * GIB, &pattern);
+    std::fs::write(format!("{}1.h2w", p), &buf).unwrap();
+    std::fs::write(format!("{}2.h2w", p), &buf).unwrap();
+
+    let mut rng = 7;
+    let stats = spot_check(&p, &[1, 2], 64, SECTOR_SIZE as u64, &pattern, &mut rng).unwrap();
+    assert!(stats.secs_overwritten() > 0);
+    assert_eq!(stats.secs_lost(), stats.secs_overwritten());
+    assert_eq!(stats.secs_ok() + stats.secs_lost(), 64);
+}
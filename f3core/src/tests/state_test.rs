@@ -0,0 +1,50 @@
+#[cfg(test)]
+use crate::{
+    flow::Flow,
+    pattern::{GeneratorKind, Pattern, PatternHint},
+    state::{ReadState, WriteState, read_state_path},
+};
+
+#[test]
+fn write_state_roundtrip_and_remove() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = dir.path().to_str().unwrap();
+
+    let mut state = WriteState::new(1, 10, Pattern::new(GeneratorKind::Lcg, 3), 0);
+    assert_eq!(state.next_file(), 1);
+    state.last_completed = Some(4);
+    state.flow = Flow::new(10 << 30, 0, false).state();
+    state.save(p).unwrap();
+
+    let loaded = WriteState::load(p).unwrap().unwrap();
+    assert_eq!(loaded, state);
+    assert_eq!(loaded.next_file(), 5);
+
+    WriteState::remove(p).unwrap();
+    assert!(WriteState::load(p).unwrap().is_none());
+    // Removing twice is fine
+    WriteState::remove(p).unwrap();
+}
+
+#[test]
+fn read_state_keeps_its_options_off_the_drive() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("nf3").join("state.json");
+
+    let mut state = ReadState::new(1, 0);
+    state.hint = PatternHint {
+        seed: 7,
+        sector_size: Some(4096),
+        ..Default::default()
+    };
+    state.save(&file).unwrap();
+    assert_eq!(ReadState::load(&file).unwrap().unwrap(), state);
+    assert!(state.describe_options().contains("seed 7"));
+    ReadState::remove(&file).unwrap();
+    assert!(ReadState::load(&file).unwrap().is_none());
+
+    // One state per drive, none of them on it
+    let default = read_state_path("/media/USB/");
+    assert!(default.ends_with("read-state-media_USB.json"));
+    assert_ne!(default, read_state_path("/media/USB2/"));
+}
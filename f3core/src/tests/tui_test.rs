@@ -0,0 +1,28 @@
+#[cfg(test)]
+use crate::{dashboard::Event, tui::App, utils::GIB, verify::FileStats};
+
+#[test]
+fn progress_by_files_without_measurements() {
+    let mut app = App::default();
+    app.apply(Event::Start {
+        tool: "read",
+        path: "/mnt/".into(),
+        total_bytes: 0,
+        files: vec![1, 2, 3, 4],
+    });
+    app.apply(Event::FileStarted(1));
+    app.apply(Event::FileDone {
+        number: 1,
+        stats: Some(FileStats::new()),
+    });
+    assert_eq!(app.current, None);
+    assert_eq!(app.ratio(), 0.25);
+
+    app.apply(Event::Progress {
+        processed: GIB * 3,
+        total: GIB * 4,
+        speed: 2048.0,
+    });
+    assert_eq!(app.ratio(), 0.75);
+    assert_eq!(app.recent_speed(), 2048.0);
+}
@@ -0,0 +1,489 @@
+// f3core/src/tui.rs
+
+//! Full-screen dashboard fed by `crate::dashboard` events.
+//!
+//! While it runs, stdout and stderr go to a temporary file that is replayed
+//! when it closes, so the usual per-file lines and the final report are not
+//! lost and do not garble the screen.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::File,
+    io::{self, IsTerminal, Seek, Write},
+    os::fd::{AsRawFd, FromRawFd, RawFd},
+    process,
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Gauge, Paragraph, Row, Sparkline, Table, Wrap},
+};
+
+use crate::{
+    dashboard::{self, Event},
+    interrupt::{EXIT_INTERRUPTED, interrupted, paused, request_stop, set_paused},
+    utils::{GIB, adjust_unit, pr_time_str},
+    verify::{FileClass, FileStats},
+};
+
+/// Speed samples kept for the graph, one per `Flow` measurement.
+const MAX_SAMPLES: usize = 512;
+const TICK: Duration = Duration::from_millis(200);
+
+struct Session {
+    tx: Sender<Event>,
+    ui: Option<JoinHandle<()>>,
+    saved_out: RawFd,
+    saved_err: RawFd,
+    log: File,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+type Term = Terminal<CrosstermBackend<File>>;
+
+pub(crate) fn start() -> io::Result<()> {
+    if !io::stdout().is_terminal() {
+        return Err(io::Error::other("stdout is not a terminal"));
+    }
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+
+    let log = tempfile::tempfile()?;
+    // The terminal is set up before anything is redirected, so a failure
+    // leaves the run with its usual output.
+    let terminal = open_terminal()?;
+
+    // SAFETY: plain descriptor juggling on descriptors owned by this process.
+    let (saved_out, saved_err) = unsafe {
+        let saved_out = libc::dup(1);
+        let saved_err = libc::dup(2);
+        if saved_out < 0 || saved_err < 0 {
+            let err = io::Error::last_os_error();
+            close_terminal(terminal);
+            return Err(err);
+        }
+        libc::dup2(log.as_raw_fd(), 1);
+        libc::dup2(log.as_raw_fd(), 2);
+        (saved_out, saved_err)
+    };
+
+    let (tx, rx) = mpsc::channel();
+    // Stored before the UI thread starts, so that it can give up the session
+    // if drawing fails.
+    *SESSION.lock().unwrap() = Some(Session {
+        tx: tx.clone(),
+        ui: None,
+        saved_out,
+        saved_err,
+        log,
+    });
+    dashboard::attach(tx);
+    let ui = thread::spawn(move || run(rx, terminal));
+    if let Some(session) = SESSION.lock().unwrap().as_mut() {
+        session.ui = Some(ui);
+    }
+    // Also give the terminal back on `process::exit`.
+    unsafe {
+        libc::atexit(restore_at_exit);
+    }
+    Ok(())
+}
+
+pub(crate) fn stop() {
+    let Some(session) = SESSION.lock().unwrap().take() else {
+        return;
+    };
+    let _ = session.tx.send(Event::Finished);
+    dashboard::detach();
+    drop(session.tx);
+    if let Some(ui) = session.ui {
+        let _ = ui.join();
+    }
+    restore(session.saved_out, session.saved_err, session.log);
+}
+
+extern "C" fn restore_at_exit() {
+    let Ok(mut guard) = SESSION.try_lock() else {
+        return;
+    };
+    if let Some(session) = guard.take() {
+        dashboard::detach();
+        let _ = disable_raw_mode();
+        let mut tty = unsafe { File::from_raw_fd(libc::dup(session.saved_out)) };
+        let _ = execute!(tty, LeaveAlternateScreen, Show);
+        restore(session.saved_out, session.saved_err, session.log);
+    }
+}
+
+/// Put stdout/stderr back and replay what was printed meanwhile.
+fn restore(saved_out: RawFd, saved_err: RawFd, mut log: File) {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    unsafe {
+        libc::dup2(saved_out, 1);
+        libc::dup2(saved_err, 2);
+        libc::close(saved_out);
+        libc::close(saved_err);
+    }
+    if log.rewind().is_ok() {
+        let _ = io::copy(&mut log, &mut io::stdout());
+    }
+}
+
+/// Raw mode and the alternate screen on a copy of stdout.
+fn open_terminal() -> io::Result<Term> {
+    // SAFETY: a new descriptor owned by the returned `File`.
+    let tty = unsafe { libc::dup(1) };
+    if tty < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut tty = unsafe { File::from_raw_fd(tty) };
+    enable_raw_mode()?;
+    if let Err(e) = execute!(tty, EnterAlternateScreen, Hide) {
+        let _ = disable_raw_mode();
+        return Err(e);
+    }
+    match Terminal::new(CrosstermBackend::new(tty)) {
+        Ok(terminal) => Ok(terminal),
+        Err(e) => {
+            let _ = disable_raw_mode();
+            let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+            Err(e)
+        }
+    }
+}
+
+fn close_terminal(mut terminal: Term) {
+    let _ = disable_raw_mode();
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen, Show);
+}
+
+fn run(rx: Receiver<Event>, mut terminal: Term) {
+    let res = event_loop(&rx, &mut terminal);
+    close_terminal(terminal);
+
+    // Drawing failed mid-run: go on without the dashboard instead of
+    // leaving a blank screen and the output in the temporary file.
+    if let Err(e) = res {
+        dashboard::detach();
+        if let Some(session) = SESSION.lock().unwrap().take() {
+            restore(session.saved_out, session.saved_err, session.log);
+        }
+        eprintln!(
+            "Warning: The dashboard failed, continuing without it: {}",
+            e
+        );
+    }
+}
+
+fn event_loop(rx: &Receiver<Event>, terminal: &mut Term) -> io::Result<()> {
+    let mut app = App::default();
+    loop {
+        match rx.recv_timeout(TICK) {
+            Ok(event) => {
+                app.apply(event);
+                while let Ok(event) = rx.try_recv() {
+                    app.apply(event);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => app.finished = true,
+        }
+        if app.finished && interrupted() {
+            return Ok(());
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+
+        while event::poll(Duration::ZERO)? {
+            let TermEvent::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if app.finished {
+                return Ok(());
+            }
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Char('p') => set_paused(true),
+                KeyCode::Char('r') => set_paused(false),
+                KeyCode::Char('q') | KeyCode::Esc => request_stop(),
+                _ if ctrl_c => {
+                    // Raw mode turns Ctrl-C into a key: same as SIGINT.
+                    if interrupted() {
+                        process::exit(EXIT_INTERRUPTED);
+                    }
+                    request_stop();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct App {
+    tool: &'static str,
+    path: String,
+    files: Vec<i64>,
+    /// Done files; `None` stats for written ones.
+    results: BTreeMap<i64, Option<FileStats>>,
+    pub(crate) current: Option<i64>,
+    processed: u64,
+    total: u64,
+    /// Instantaneous speeds in KB/s.
+    speeds: VecDeque<u64>,
+    finished: bool,
+}
+
+impl App {
+    pub(crate) fn apply(&mut self, event: Event) {
+        match event {
+            Event::Start {
+                tool,
+                path,
+                total_bytes,
+                files,
+            } => {
+                *self = App {
+                    tool,
+                    path,
+                    files,
+                    total: total_bytes,
+                    ..Default::default()
+                };
+            }
+            Event::FileStarted(n) => self.current = Some(n),
+            Event::Progress {
+                processed,
+                total,
+                speed,
+            } => {
+                self.processed = processed;
+                self.total = total;
+                if self.speeds.len() == MAX_SAMPLES {
+                    self.speeds.pop_front();
+                }
+                self.speeds.push_back((speed / 1024.0) as u64);
+            }
+            Event::FileDone { number, stats } => {
+                self.results.insert(number, stats);
+                if self.current == Some(number) {
+                    self.current = None;
+                }
+            }
+            Event::Finished => self.finished = true,
+        }
+    }
+
+    /// Fraction done; by files when `Flow` does not measure (quick, sample).
+    pub(crate) fn ratio(&self) -> f64 {
+        if self.processed > 0 && self.total > 0 {
+            (self.processed as f64 / self.total as f64).min(1.0)
+        } else if !self.files.is_empty() {
+            self.results.len() as f64 / self.files.len() as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Mean of the recent samples, in bytes/s.
+    pub(crate) fn recent_speed(&self) -> f64 {
+        let recent: Vec<u64> = self.speeds.iter().rev().take(30).copied().collect();
+        if recent.is_empty() {
+            return 0.0;
+        }
+        recent.iter().sum::<u64>() as f64 * 1024.0 / recent.len() as f64
+    }
+
+    fn status(&self) -> (&'static str, Color) {
+        if self.finished {
+            ("DONE - press any key to close", Color::Green)
+        } else if interrupted() {
+            ("ABORTING", Color::Red)
+        } else if paused() {
+            ("PAUSED", Color::Yellow)
+        } else {
+            ("RUNNING", Color::Cyan)
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [title, gauge, middle, table, keys] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(10),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let (status, color) = self.status();
+        let current = match self.current {
+            Some(n) => format!(" - {}.h2w", n),
+            None => String::new(),
+        };
+        frame.render_widget(
+            Line::from(vec![
+                Span::raw(format!("nf3_{} {}{}  ", self.tool, self.path, current)),
+                Span::styled(status, Style::default().fg(color)),
+            ]),
+            title,
+        );
+
+        let speed = self.recent_speed();
+        let (s, unit) = adjust_unit(speed);
+        let mut label = format!("{:.1}% - {:.2} {}/s", self.ratio() * 100.0, s, unit);
+        if speed > 0.0 && self.total > self.processed {
+            label.push_str(" - ETA ");
+            label.push_str(&pr_time_str((self.total - self.processed) as f64 / speed));
+        }
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::default().borders(Borders::ALL).title("Progress"))
+                .gauge_style(Style::default().fg(Color::Cyan))
+                .ratio(self.ratio())
+                .label(label),
+            gauge,
+        );
+
+        let [graph, map] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(middle);
+        let width = graph.width.saturating_sub(2) as usize;
+        let shown: Vec<u64> = self
+            .speeds
+            .iter()
+            .skip(self.speeds.len().saturating_sub(width))
+            .copied()
+            .collect();
+        let (max, unit) = adjust_unit(shown.iter().max().copied().unwrap_or(0) as f64 * 1024.0);
+        frame.render_widget(
+            Sparkline::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("Speed (max {:.1} {}/s)", max, unit)),
+                )
+                .style(Style::default().fg(Color::Green))
+                .data(&shown),
+            graph,
+        );
+        frame.render_widget(self.capacity_map(), map);
+        frame.render_widget(self.table(table.height.saturating_sub(3) as usize), table);
+        frame.render_widget(Line::from(" p: pause   r: resume   q/Esc: abort"), keys);
+    }
+
+    /// One cell per GiB file, coloured by its dominant sector class.
+    fn capacity_map(&self) -> Paragraph<'_> {
+        let mut cells: Vec<Span> = self
+            .files
+            .iter()
+            .map(|n| match self.results.get(n) {
+                Some(stats) => Span::styled("█", Style::default().fg(class_color(stats.as_ref()))),
+                None if self.current == Some(*n) => {
+                    Span::styled("▒", Style::default().fg(Color::Cyan))
+                }
+                None => Span::styled("░", Style::default().fg(Color::DarkGray)),
+            })
+            .collect();
+        if cells.is_empty() {
+            cells.push(Span::raw(""));
+        }
+        let legend = [
+            ("ok", Color::Green),
+            ("corrupted", Color::Red),
+            ("changed", Color::Yellow),
+            ("overwritten", Color::Magenta),
+            ("stale", Color::Blue),
+            ("erased", Color::Gray),
+        ]
+        .iter()
+        .flat_map(|(name, color)| {
+            [
+                Span::styled("█", Style::default().fg(*color)),
+                Span::raw(format!("{} ", name)),
+            ]
+        })
+        .collect::<Vec<_>>();
+        Paragraph::new(vec![Line::from(cells), Line::from(""), Line::from(legend)])
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Capacity map (1 cell = {} GiB)", GIB >> 30)),
+            )
+    }
+
+    /// The last `rows` finished files.
+    fn table(&self, rows: usize) -> Table<'_> {
+        let header = [
+            "File",
+            "ok",
+            "corrupted",
+            "changed",
+            "overwritten",
+            "stale",
+            "zeroed",
+            "ones",
+        ];
+        let body = self
+            .results
+            .iter()
+            .rev()
+            .take(rows)
+            .rev()
+            .map(|(n, stats)| {
+                let mut cells = vec![format!("{}.h2w", n)];
+                match stats {
+                    Some(s) => cells.extend(
+                        [
+                            s.secs_ok(),
+                            s.secs_corrupted(),
+                            s.secs_changed(),
+                            s.secs_overwritten(),
+                            s.secs_stale(),
+                            s.secs_zeroed(),
+                            s.secs_ones(),
+                        ]
+                        .map(|c| c.to_string()),
+                    ),
+                    None => cells.push("written".to_string()),
+                }
+                Row::new(cells).style(Style::default().fg(class_color(stats.as_ref())))
+            });
+        Table::new(body, [Constraint::Length(10); 8])
+            .header(Row::new(header).style(Style::default().fg(Color::Cyan)))
+            .block(Block::default().borders(Borders::ALL).title("Files"))
+    }
+}
+
+fn class_color(stats: Option<&FileStats>) -> Color {
+    match stats.map_or(FileClass::Ok, FileStats::class) {
+        FileClass::Ok => Color::Green,
+        FileClass::Corrupted => Color::Red,
+        FileClass::Changed => Color::Yellow,
+        FileClass::Overwritten => Color::Magenta,
+        FileClass::Stale => Color::Blue,
+        FileClass::Erased => Color::Gray,
+    }
+}
@@ -1,10 +1,11 @@
+use serde::{Serialize, de::DeserializeOwned};
 use std::{
     env,
     fs::{self, File},
-    io::Result,
-    path::Path,
+    io::{self, Result},
+    path::{Path, PathBuf},
     process,
-    time::Instant,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 pub const SECTOR_SIZE: usize = 512;
@@ -39,6 +40,39 @@ pub fn adjust_dev_path(dev_path: &mut String) {
     // }
 }
 
+/// Load `path` as JSON, `Ok(None)` if the file doesn't exist.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_str(&data)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write `value` as JSON to `path`.
+///
+/// The file is rewritten in place rather than through a temporary file:
+/// by the end of a run the drive is full, and a file that doesn't grow
+/// still fits into its previous blocks.
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let data = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+    fs::write(path, data)
+}
+
+/// Directory for data kept off the drive under test: `$XDG_DATA_HOME/nf3`,
+/// `~/.local/share/nf3` or `%APPDATA%\nf3`.
+pub fn data_dir() -> PathBuf {
+    let base = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| Path::new(&h).join(".local/share")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("nf3")
+}
+
 pub fn adjust_unit(bytes: f64) -> (f64, &'static str) {
     let units = ["Bytes", "KB", "MB", "GB", "TB"];
     let mut result = bytes;
@@ -129,6 +163,54 @@ pub fn random_number(prv_number: u64) -> u64 {
     prv_number.wrapping_mul(4_294_967_311u64).wrapping_add(17)
 }
 
+/// Pick a non-zero seed for a new run from the clock and the process id.
+pub fn new_run_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    let seed = random_number(nanos ^ ((process::id() as u64) << 32));
+    if seed == 0 { 1 } else { seed }
+}
+
+/// Name of this machine, recorded in manifests and reports.
+pub fn host_name() -> String {
+    #[cfg(unix)]
+    {
+        let mut buf = [0u8; 256];
+        let rc = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+        if rc == 0 {
+            let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+            return String::from_utf8_lossy(&buf[..len]).into_owned();
+        }
+    }
+
+    env::var("COMPUTERNAME")
+        .or_else(|_| env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Total and free bytes of the filesystem holding `path`, if known.
+pub fn fs_space(path: &str) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        let cpath = std::ffi::CString::new(path).ok()?;
+        let mut s: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(cpath.as_ptr(), &mut s) } != 0 {
+            return None;
+        }
+        #[allow(clippy::unnecessary_cast)]
+        let frsize = s.f_frsize as u64;
+        #[allow(clippy::unnecessary_cast)]
+        Some((s.f_blocks as u64 * frsize, s.f_bavail as u64 * frsize))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
 #[rustfmt::skip]
 pub fn fadvise_dontneed(file: &File) -> Result<()> {
     // Crashing Win and Mac tests, don't sure if it's really needed (6% faster with it)
@@ -150,6 +232,27 @@ pub fn fadvise_dontneed(file: &File) -> Result<()> {
     Ok(())
 }
 
+/// Reserve `size` bytes for `file` without writing them, so a quick run still
+/// takes the whole capacity. Falls back to `set_len` (a sparse file) where
+/// fallocate is not available.
+pub fn allocate(file: &File, size: u64) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd;
+
+        let rc = unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, size as libc::off_t) };
+        if rc == 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EOPNOTSUPP) {
+            return Err(err);
+        }
+    }
+
+    file.set_len(size)
+}
+
 #[rustfmt::skip]
 pub fn fadvise_sequential(file: &File) -> Result<()> {
     // Crashing Win and Mac tests, don't sure if it's really needed (6% faster with it)
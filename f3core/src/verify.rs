@@ -1,9 +1,12 @@
 use std::{
     cmp::min,
+    collections::BTreeMap,
     fs::File,
-    io::{self, ErrorKind, Read, Result, Write},
+    io::{self, ErrorKind, Read, Result, Seek, SeekFrom, Write},
 };
 
+use serde::{Deserialize, Serialize};
+
 use crossterm::{
     cursor::MoveToPreviousLine,
     execute,
@@ -13,20 +16,156 @@ use crossterm::{
 
 // from the shared core crate:
 use crate::{
+    file_fill::MARKER_SPACING,
     flow::{DynamicBuffer, Flow},
+    interrupt::interrupted,
+    pattern::{ByteOrder, GeneratorKind, Pattern, PatternHint, pattern_word},
     utils::{GIB, SECTOR_SIZE, fadvise_dontneed, fadvise_sequential, random_number},
 };
 
+/// What a whole file is shown as on the capacity maps of the dashboard and
+/// the HTML report: ok, or the class most of its lost sectors fall into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileClass {
+    Ok,
+    Corrupted,
+    Changed,
+    Overwritten,
+    Stale,
+    /// Zero- or ones-filled.
+    Erased,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FileStats {
     secs_ok: u64,
     secs_corrupted: u64,
     secs_changed: u64,
     secs_overwritten: u64,
+    secs_stale: u64,
+    /// Erased or never written: all 0x00 / all 0xFF.
+    #[serde(default)]
+    secs_zeroed: u64,
+    #[serde(default)]
+    secs_ones: u64,
+    #[serde(default)]
+    bit_errors: BitErrors,
+    /// Overwritten sectors by the file their data belongs to.
+    #[serde(default)]
+    overwritten_by: BTreeMap<i64, u64>,
 
     bytes_read: u64,
     read_all: bool,
 }
 
+/// Flipped bits of the "changed" sectors, to tell single-bit flips (aging
+/// NAND) from stuck bytes or systematic controller bugs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BitErrors {
+    /// `per_sector[n]` = changed sectors with `n` flipped bits.
+    pub per_sector: Vec<u64>,
+    /// Flipped bits by (index of the word in the sector, index of the bit
+    /// in the word, 0 = least significant).
+    #[serde(with = "positions")]
+    pub by_position: BTreeMap<(u32, u8), u64>,
+    /// Bits read as 1 that were written as 0.
+    pub set: u64,
+    /// Bits read as 0 that were written as 1.
+    pub cleared: u64,
+}
+
+impl BitErrors {
+    /// Record the bits of `sector` that differ from `expected`.
+    fn record(&mut self, sector: &[u8], expected: &[u8], order: ByteOrder) {
+        let mut flipped = 0;
+        for (i, (word, exp)) in sector
+            .chunks_exact(8)
+            .zip(expected.chunks_exact(8))
+            .enumerate()
+        {
+            let (word, exp) = (order.read_word(word), order.read_word(exp));
+            let diff = word ^ exp;
+            if diff == 0 {
+                continue;
+            }
+            flipped += diff.count_ones() as usize;
+            for bit in 0..64 {
+                if diff & (1 << bit) != 0 {
+                    *self.by_position.entry((i as u32, bit)).or_default() += 1;
+                }
+            }
+            self.set += (diff & word).count_ones() as u64;
+            self.cleared += (diff & exp).count_ones() as u64;
+        }
+        add_at(&mut self.per_sector, flipped, 1);
+    }
+
+    pub fn merge(&mut self, other: &BitErrors) {
+        for (i, &n) in other.per_sector.iter().enumerate() {
+            add_at(&mut self.per_sector, i, n);
+        }
+        for (&pos, &n) in &other.by_position {
+            *self.by_position.entry(pos).or_default() += n;
+        }
+        self.set += other.set;
+        self.cleared += other.cleared;
+    }
+
+    /// Flipped bits by index of the word in the sector.
+    pub fn by_word(&self) -> Vec<u64> {
+        let mut counts = Vec::new();
+        for (&(word, _), &n) in &self.by_position {
+            add_at(&mut counts, word as usize, n);
+        }
+        counts
+    }
+
+    /// Flipped bits by index of the bit in the word.
+    pub fn by_bit(&self) -> Vec<u64> {
+        let mut counts = Vec::new();
+        for (&(_, bit), &n) in &self.by_position {
+            add_at(&mut counts, bit as usize, n);
+        }
+        counts
+    }
+
+    pub fn flipped_bits(&self) -> u64 {
+        self.set + self.cleared
+    }
+}
+
+/// JSON objects only have string keys, `by_position` is stored as a list
+/// of `[word, bit, count]`.
+mod positions {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        map: &BTreeMap<(u32, u8), u64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map.iter().map(|(&(word, bit), &n)| (word, bit, n)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<(u32, u8), u64>, D::Error> {
+        let list = Vec::<(u32, u8, u64)>::deserialize(deserializer)?;
+        Ok(list
+            .into_iter()
+            .map(|(word, bit, n)| ((word, bit), n))
+            .collect())
+    }
+}
+
+fn add_at(counts: &mut Vec<u64>, i: usize, n: u64) {
+    if counts.len() <= i {
+        counts.resize(i + 1, 0);
+    }
+    counts[i] += n;
+}
+
 impl Default for FileStats {
     fn default() -> Self {
         Self::new()
@@ -40,6 +179,11 @@ impl FileStats {
             secs_corrupted: 0,
             secs_changed: 0,
             secs_overwritten: 0,
+            secs_stale: 0,
+            secs_zeroed: 0,
+            secs_ones: 0,
+            bit_errors: BitErrors::default(),
+            overwritten_by: BTreeMap::new(),
             bytes_read: 0,
             read_all: false,
         }
@@ -62,6 +206,26 @@ impl FileStats {
         self.secs_overwritten
     }
     #[inline]
+    pub fn secs_stale(&self) -> u64 {
+        self.secs_stale
+    }
+    #[inline]
+    pub fn secs_zeroed(&self) -> u64 {
+        self.secs_zeroed
+    }
+    #[inline]
+    pub fn secs_ones(&self) -> u64 {
+        self.secs_ones
+    }
+    #[inline]
+    pub fn bit_errors(&self) -> &BitErrors {
+        &self.bit_errors
+    }
+    #[inline]
+    pub fn overwritten_by(&self) -> &BTreeMap<i64, u64> {
+        &self.overwritten_by
+    }
+    #[inline]
     pub fn bytes_read(&self) -> u64 {
         self.bytes_read
     }
@@ -70,7 +234,55 @@ impl FileStats {
         self.read_all
     }
 
-    pub fn validate_file(&mut self, path: &str, number: i32, flow: &mut Flow) -> Result<()> {
+    /// Add the counters of `other` to these, e.g. to get the totals of a run.
+    pub fn add(&mut self, other: &FileStats) {
+        self.secs_ok += other.secs_ok;
+        self.secs_corrupted += other.secs_corrupted;
+        self.secs_changed += other.secs_changed;
+        self.secs_overwritten += other.secs_overwritten;
+        self.secs_stale += other.secs_stale;
+        self.secs_zeroed += other.secs_zeroed;
+        self.secs_ones += other.secs_ones;
+        self.bit_errors.merge(&other.bit_errors);
+        for (&number, &n) in &other.overwritten_by {
+            *self.overwritten_by.entry(number).or_default() += n;
+        }
+        self.bytes_read += other.bytes_read;
+    }
+
+    /// Sectors that didn't read back as written.
+    pub fn secs_lost(&self) -> u64 {
+        self.secs_corrupted
+            + self.secs_changed
+            + self.secs_overwritten
+            + self.secs_stale
+            + self.secs_zeroed
+            + self.secs_ones
+    }
+
+    pub fn class(&self) -> FileClass {
+        if self.secs_lost() == 0 {
+            return FileClass::Ok;
+        }
+        [
+            (self.secs_corrupted, FileClass::Corrupted),
+            (self.secs_changed, FileClass::Changed),
+            (self.secs_overwritten, FileClass::Overwritten),
+            (self.secs_stale, FileClass::Stale),
+            (self.secs_zeroed + self.secs_ones, FileClass::Erased),
+        ]
+        .into_iter()
+        .max_by_key(|(count, _)| *count)
+        .map_or(FileClass::Corrupted, |(_, class)| class)
+    }
+
+    pub fn validate_file(
+        &mut self,
+        path: &str,
+        number: i32,
+        pattern: &Pattern,
+        flow: &mut Flow,
+    ) -> Result<()> {
         let full_fn = &format!("{}{}.h2w", path, number);
         let mut bytes_read: isize = 0;
 
@@ -102,6 +314,7 @@ impl FileStats {
                 &mut file,
                 &mut expected_offset,
                 &mut rem_chunk_size,
+                pattern,
                 self,
             );
             if bytes_read == 0 {
@@ -112,6 +325,9 @@ impl FileStats {
                 break; // Error reading file
             }
             flow.measure(&file, bytes_read as i64)?;
+            if interrupted() {
+                break;
+            }
         }
 
         self.print_status(&val_str);
@@ -126,12 +342,15 @@ impl FileStats {
             Clear(ClearType::CurrentLine),
             MoveToPreviousLine(1),
             Print(format!(
-                "{}{:>7}/{:>9}/{:>7}/{:>7}\n",
+                "{}{:>7}/{:>9}/{:>7}/{:>7}/{:>7}/{:>7}/{:>7}\n",
                 current_str,
                 self.secs_ok,
                 self.secs_corrupted,
                 self.secs_changed,
-                self.secs_overwritten
+                self.secs_overwritten,
+                self.secs_stale,
+                self.secs_zeroed,
+                self.secs_ones
             ))
         )
         .unwrap();
@@ -143,6 +362,7 @@ fn check_chunk(
     file: &mut File,
     expected_offset: &mut u64,
     remaining_chunk_size: &mut u64,
+    pattern: &Pattern,
     stats: &mut FileStats,
 ) -> isize {
     let buf = dbuf.get_buf(*remaining_chunk_size as usize);
@@ -169,6 +389,7 @@ fn check_chunk(
             &buf[..filled as usize],
             filled as usize,
             *expected_offset,
+            pattern,
             stats,
         );
     }
@@ -178,54 +399,289 @@ fn check_chunk(
     total_bytes_read
 }
 
-fn check_buffer(buf: &[u8], size: usize, mut expected_offset: u64, stats: &mut FileStats) -> u64 {
-    assert!(size % SECTOR_SIZE == 0);
+pub(crate) fn check_buffer(
+    buf: &[u8],
+    size: usize,
+    mut expected_offset: u64,
+    pattern: &Pattern,
+    stats: &mut FileStats,
+) -> u64 {
+    let sector_size = pattern.sector_size;
+    assert!(size.is_multiple_of(sector_size));
 
-    for i in (0..size).step_by(SECTOR_SIZE) {
-        let sector = &buf[i..i + SECTOR_SIZE];
-        check_sector(sector, expected_offset, stats);
-        expected_offset += SECTOR_SIZE as u64;
+    for sector in buf[..size].chunks_exact(sector_size) {
+        check_sector(sector, expected_offset, pattern, stats);
+        expected_offset += sector_size as u64;
     }
 
     expected_offset
 }
 
+/// Default number of words a sector may differ by and still count as "changed".
 pub const TOLERANCE: usize = 2;
 
-fn read_offset(buf: &[u8]) -> u64 {
-    let mut arr = [0u8; 8];
-    arr.copy_from_slice(&buf[..8]);
-    u64::from_ne_bytes(arr)
+/// Run `f` on the sector `pattern` generates for `header`, without
+/// allocating for the default sector size.
+#[inline]
+fn with_expected<R>(header: u64, pattern: &Pattern, f: impl FnOnce(&[u8]) -> R) -> R {
+    if pattern.sector_size <= SECTOR_SIZE {
+        let mut expected = [0u8; SECTOR_SIZE];
+        let expected = &mut expected[..pattern.sector_size];
+        pattern.fill_sector(expected, header);
+        f(expected)
+    } else {
+        let mut expected = vec![0u8; pattern.sector_size];
+        pattern.fill_sector(&mut expected, header);
+        f(&expected)
+    }
 }
 
-fn check_sector(sector: &[u8], expected_offset: u64, stats: &mut FileStats) {
-    assert_eq!(SECTOR_SIZE, sector.len());
-    assert_eq!(SECTOR_SIZE % std::mem::size_of::<u64>(), 0);
-
-    let first_word = read_offset(&sector[..8]);
-    let mut rn = first_word;
+/// Count the words of `sector` that differ from the data `pattern` generates
+/// for `header`, giving up once the count exceeds the tolerance.
+fn count_errors(sector: &[u8], header: u64, pattern: &Pattern) -> usize {
     let mut error_count = 0;
 
-    for word in sector.chunks_exact(8) {
-        if word != rn.to_ne_bytes() {
-            error_count += 1;
-            if error_count > TOLERANCE {
-                break;
+    // The LCG is cheap enough to compare word by word, without generating
+    // the whole expected sector first.
+    if pattern.kind == GeneratorKind::Lcg {
+        let mut rn = header ^ pattern.seed;
+        for (i, word) in sector.chunks_exact(8).enumerate() {
+            if pattern.order.read_word(word) != pattern_word(i, header, pattern.seed, rn) {
+                error_count += 1;
+                if error_count > pattern.tolerance {
+                    break;
+                }
+            }
+            rn = random_number(rn);
+        }
+        return error_count;
+    }
+
+    with_expected(header, pattern, |expected| {
+        for (word, expected_word) in sector.chunks_exact(8).zip(expected.chunks_exact(8)) {
+            if word != expected_word {
+                error_count += 1;
+                if error_count > pattern.tolerance {
+                    break;
+                }
             }
         }
-        rn = random_number(rn);
+
+        error_count
+    })
+}
+
+/// True if `sector` holds valid data of a pattern other than `pattern`
+/// (another seed, or no seed at all), i.e. written by an earlier run
+/// or served from a stale cache.
+fn is_stale(sector: &[u8], header: u64, pattern: &Pattern) -> bool {
+    let stored_seed = pattern.order.read_word(&sector[8..16]);
+
+    GeneratorKind::ALL.iter().any(|&kind| {
+        [stored_seed, 0].iter().any(|&seed| {
+            let other = Pattern {
+                kind,
+                seed,
+                ..*pattern
+            };
+            other != *pattern && count_errors(sector, header, &other) <= pattern.tolerance
+        })
+    })
+}
+
+/// Guess the generator and byte order `path` was written with, trying only
+/// what `hint` leaves open. The first sector of every MiB is tried in turn,
+/// so a few overwritten or corrupted sectors don't hide the pattern.
+/// Without a seed in `hint`, the seed stored in word 1 of the sector is
+/// tried too, so seeded files read without their manifest are not stale.
+/// Returns `None` if no sector matches.
+pub fn detect_pattern(path: &str, hint: &PatternHint) -> Option<Pattern> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut sector = vec![0u8; hint.fallback().sector_size];
+
+    let kinds = hint.kind.map_or(GeneratorKind::ALL.to_vec(), |k| vec![k]);
+    let orders = hint.order.map_or(ByteOrder::ALL.to_vec(), |o| vec![o]);
+
+    (0..len)
+        .step_by(MARKER_SPACING as usize)
+        .find_map(|offset| {
+            file.seek(SeekFrom::Start(offset)).ok()?;
+            file.read_exact(&mut sector).ok()?;
+            kinds.iter().find_map(|&kind| {
+                orders.iter().find_map(|&order| {
+                    // The sector's own header, an overwritten sector still tells
+                    // the pattern
+                    let header = order.read_word(&sector[..8]);
+                    let stored_seed = order.read_word(&sector[8..16]);
+                    let seeds = if hint.seed == 0 && stored_seed != 0 {
+                        vec![0, stored_seed]
+                    } else {
+                        vec![hint.seed]
+                    };
+                    seeds.into_iter().find_map(|seed| {
+                        let pattern = Pattern {
+                            seed,
+                            ..hint.pattern(kind, order)
+                        };
+                        (count_errors(&sector, header, &pattern) <= pattern.tolerance)
+                            .then_some(pattern)
+                    })
+                })
+            })
+        })
+}
+
+/// Read back `samples` random sectors of the files `<path>/<number>.h2w`
+/// of `files`, after dropping them from the page cache. Only sectors at
+/// multiples of `spacing` are picked, i.e. the markers of a quick run.
+/// `rng` is advanced, so consecutive calls pick different sectors.
+pub fn spot_check(
+    path: &str,
+    files: &[i64],
+    samples: usize,
+    spacing: u64,
+    pattern: &Pattern,
+    rng: &mut u64,
+) -> Result<FileStats> {
+    let mut stats = FileStats::new();
+    if files.is_empty() {
+        return Ok(stats);
     }
 
-    if expected_offset == first_word {
+    let mut picks: Vec<i64> = (0..samples)
+        .map(|_| {
+            *rng = random_number(*rng);
+            files[((*rng >> 32) % files.len() as u64) as usize]
+        })
+        .collect();
+    picks.sort_unstable();
+
+    for group in picks.chunk_by(|a, b| a == b) {
+        let number = group[0];
+        let mut file = File::open(format!("{}{}.h2w", path, number))?;
+        check_random_sectors(
+            &mut file,
+            number,
+            group.len(),
+            spacing,
+            pattern,
+            rng,
+            &mut stats,
+        )?;
+    }
+
+    Ok(stats)
+}
+
+/// Check `samples` randomly chosen sectors of `<path>/<number>.h2w` instead
+/// of reading it all. The choice depends only on `seed` and `number`, so a
+/// run can be reproduced. `spacing` is as for `spot_check`.
+pub fn sample_file(
+    path: &str,
+    number: i64,
+    samples: usize,
+    spacing: u64,
+    pattern: &Pattern,
+    seed: u64,
+) -> Result<FileStats> {
+    let mut stats = FileStats::new();
+    let mut rng = random_number(seed ^ (number as u64).wrapping_mul(GIB));
+    let mut file = File::open(format!("{}{}.h2w", path, number))?;
+    check_random_sectors(
+        &mut file, number, samples, spacing, pattern, &mut rng, &mut stats,
+    )?;
+    Ok(stats)
+}
+
+/// Check the marker sectors of `<path>/<number>.h2w`, written by a quick run.
+pub fn check_markers(path: &str, number: i64, pattern: &Pattern) -> Result<FileStats> {
+    let mut stats = FileStats::new();
+    let mut file = File::open(format!("{}{}.h2w", path, number))?;
+    let size = file.metadata()?.len();
+    fadvise_dontneed(&file)?;
+
+    let mut sector = vec![0u8; pattern.sector_size];
+    for offset in (0..size).step_by(MARKER_SPACING as usize) {
+        if offset + sector.len() as u64 > size || interrupted() {
+            break;
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut sector)?;
+        check_sector(&sector, number as u64 * GIB + offset, pattern, &mut stats);
+        stats.bytes_read += sector.len() as u64;
+    }
+
+    Ok(stats)
+}
+
+fn check_random_sectors(
+    file: &mut File,
+    number: i64,
+    samples: usize,
+    spacing: u64,
+    pattern: &Pattern,
+    rng: &mut u64,
+    stats: &mut FileStats,
+) -> Result<()> {
+    let sector_size = pattern.sector_size as u64;
+    let len = file.metadata()?.len();
+    if len < sector_size {
+        return Ok(());
+    }
+    // The last slot must hold a whole sector
+    let slots = (len + spacing - sector_size) / spacing;
+    fadvise_dontneed(file)?;
+
+    let mut sector = vec![0u8; pattern.sector_size];
+    for _ in 0..samples {
+        *rng = random_number(*rng);
+        let offset = (*rng >> 32) % slots * spacing;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut sector)?;
+        check_sector(&sector, number as u64 * GIB + offset, pattern, stats);
+        stats.bytes_read += sector_size;
+    }
+    Ok(())
+}
+
+fn check_sector(sector: &[u8], expected_offset: u64, pattern: &Pattern, stats: &mut FileStats) {
+    assert_eq!(pattern.sector_size, sector.len());
+    assert_eq!(sector.len() % std::mem::size_of::<u64>(), 0);
+
+    // No pattern sector is uniform, these were erased or never written
+    if sector.iter().all(|&b| b == 0x00) {
+        stats.secs_zeroed += 1;
+        return;
+    }
+    if sector.iter().all(|&b| b == 0xFF) {
+        stats.secs_ones += 1;
+        return;
+    }
+
+    let first_word = pattern.order.read_word(&sector[..8]);
+    let error_count = count_errors(sector, first_word, pattern);
+
+    if error_count > pattern.tolerance && is_stale(sector, first_word, pattern) {
+        stats.secs_stale += 1;
+    } else if expected_offset == first_word {
         if error_count == 0 {
             stats.secs_ok += 1;
-        } else if error_count <= TOLERANCE {
+        } else if error_count <= pattern.tolerance {
             stats.secs_changed += 1;
+            with_expected(first_word, pattern, |expected| {
+                stats.bit_errors.record(sector, expected, pattern.order)
+            });
         } else {
             stats.secs_corrupted += 1;
         }
-    } else if error_count <= TOLERANCE {
+    } else if error_count <= pattern.tolerance {
         stats.secs_overwritten += 1;
+        // Every file starts at `number * GIB`
+        *stats
+            .overwritten_by
+            .entry((first_word / GIB) as i64)
+            .or_default() += 1;
     } else {
         stats.secs_corrupted += 1;
     }
@@ -253,13 +709,15 @@ fn read_all(file: &mut File, buf: &mut [u8]) -> isize {
 }
 
 #[cfg(test)]
-use crate::tests::helpers::{assert_counts, bump_words, gen_ok_sector, write_word_ne};
+use crate::tests::helpers::{
+    assert_counts, bump_words, gen_ok_sector, gen_seeded_sector, lcg, write_word_le,
+};
 
 #[test]
 fn sector_ok() {
     let mut stats = FileStats::new();
     let sector = gen_ok_sector(0);
-    check_sector(&sector, 0, &mut stats);
+    check_sector(&sector, 0, &Pattern::default(), &mut stats);
     assert_counts(&stats, 1, 0, 0, 0);
 }
 
@@ -274,7 +732,7 @@ fn sector_changed_le_tolerance() {
     }
     bump_words(&mut sector, &to_bump);
 
-    check_sector(&sector, 512, &mut stats);
+    check_sector(&sector, 512, &Pattern::default(), &mut stats);
     assert_counts(&stats, 0, 0, 1, 0);
 }
 
@@ -290,7 +748,7 @@ fn sector_corrupted_gt_tolerance_header_ok() {
     }
     bump_words(&mut sector, &to_bump);
 
-    check_sector(&sector, 1024, &mut stats);
+    check_sector(&sector, 1024, &Pattern::default(), &mut stats);
     assert_counts(&stats, 0, 1, 0, 0);
 }
 
@@ -301,9 +759,9 @@ fn sector_overwritten_header_wrong_errors_le_tolerance() {
 
     // Getting "overwritten": offset != expected_offset,
     // but the other words match (error_count == 0 <= TOLERANCE)
-    write_word_ne(&mut sector, 0, 0);
+    write_word_le(&mut sector, 0, 0);
 
-    check_sector(&sector, 2048, &mut stats);
+    check_sector(&sector, 2048, &Pattern::default(), &mut stats);
     assert_counts(&stats, 0, 0, 0, 1);
 }
 
@@ -313,17 +771,158 @@ fn sector_corrupted_header_wrong_errors_gt_tolerance() {
     let mut sector = gen_ok_sector(4096);
 
     // Incorrect header + > TOLERANCE corrupted
-    write_word_ne(&mut sector, 0, 0xDEAD_BEEF);
+    write_word_le(&mut sector, 0, 0xDEAD_BEEF);
     let mut to_bump = Vec::new();
     for i in 1..=(TOLERANCE + 1) {
         to_bump.push(i);
     }
     bump_words(&mut sector, &to_bump);
 
-    check_sector(&sector, 4096, &mut stats);
+    check_sector(&sector, 4096, &Pattern::default(), &mut stats);
+    assert_counts(&stats, 0, 1, 0, 0);
+}
+
+#[test]
+fn sector_seeded_ok() {
+    let mut stats = FileStats::new();
+    let sector = gen_seeded_sector(8192, 0xC0FFEE);
+    check_sector(&sector, 8192, &lcg(0xC0FFEE), &mut stats);
+    assert_counts(&stats, 1, 0, 0, 0);
+    assert_eq!(stats.secs_stale(), 0);
+}
+
+#[test]
+fn sector_stale_other_seed() {
+    let mut stats = FileStats::new();
+    let sector = gen_seeded_sector(8192, 0xC0FFEE);
+    check_sector(&sector, 8192, &lcg(0xBEEF), &mut stats);
+    assert_counts(&stats, 0, 0, 0, 0);
+    assert_eq!(stats.secs_stale(), 1);
+}
+
+#[test]
+fn sector_stale_unseeded_data() {
+    // A leftover file from an unseeded run read back by a seeded run
+    let mut stats = FileStats::new();
+    let sector = gen_ok_sector(8192);
+    check_sector(&sector, 8192, &lcg(0xBEEF), &mut stats);
+    assert_counts(&stats, 0, 0, 0, 0);
+    assert_eq!(stats.secs_stale(), 1);
+}
+
+#[test]
+fn sector_seeded_changed_seed_word() {
+    // A flipped seed word is a changed sector, not stale data
+    let mut stats = FileStats::new();
+    let mut sector = gen_seeded_sector(8192, 0xC0FFEE);
+    bump_words(&mut sector, &[1]);
+    check_sector(&sector, 8192, &lcg(0xC0FFEE), &mut stats);
+    assert_counts(&stats, 0, 0, 1, 0);
+    assert_eq!(stats.secs_stale(), 0);
+}
+
+#[test]
+fn sector_chacha_ok_and_stale() {
+    let chacha = Pattern::new(GeneratorKind::Chacha, 0xC0FFEE);
+    let mut sector = [0u8; SECTOR_SIZE];
+    chacha.fill_sector(&mut sector, 8192);
+
+    let mut stats = FileStats::new();
+    check_sector(&sector, 8192, &chacha, &mut stats);
+    assert_counts(&stats, 1, 0, 0, 0);
+
+    // Read back by a run that used the LCG or another seed
+    let mut stats = FileStats::new();
+    check_sector(&sector, 8192, &lcg(0xC0FFEE), &mut stats);
+    check_sector(
+        &sector,
+        8192,
+        &Pattern::new(GeneratorKind::Chacha, 7),
+        &mut stats,
+    );
+    assert_counts(&stats, 0, 0, 0, 0);
+    assert_eq!(stats.secs_stale(), 2);
+}
+
+#[test]
+fn sector_zeroed_and_ones() {
+    let mut stats = FileStats::new();
+    check_sector(&[0x00; SECTOR_SIZE], 512, &Pattern::default(), &mut stats);
+    check_sector(&[0xFF; SECTOR_SIZE], 512, &Pattern::default(), &mut stats);
+    assert_counts(&stats, 0, 0, 0, 0);
+    assert_eq!((stats.secs_zeroed(), stats.secs_ones()), (1, 1));
+    assert_eq!(stats.secs_lost(), 2);
+
+    // A single stray byte makes it garbage again
+    let mut sector = [0x00; SECTOR_SIZE];
+    sector[100] = 1;
+    check_sector(&sector, 512, &Pattern::default(), &mut stats);
     assert_counts(&stats, 0, 1, 0, 0);
 }
 
+#[test]
+fn sector_changed_bit_errors() {
+    let mut stats = FileStats::new();
+    let mut sector = gen_ok_sector(512);
+    // One flipped bit in word 3, a stuck byte (two bits set) in word 5
+    sector[3 * 8] ^= 0b0000_0100;
+    let before = sector[5 * 8 + 1];
+    sector[5 * 8 + 1] = 0xFF;
+    let set_in_byte = (!before).count_ones() as u64;
+
+    check_sector(&sector, 512, &Pattern::default(), &mut stats);
+    assert_counts(&stats, 0, 0, 1, 0);
+
+    let bits = stats.bit_errors();
+    assert_eq!(bits.flipped_bits(), 1 + set_in_byte);
+    assert_eq!(bits.per_sector.iter().sum::<u64>(), 1);
+    assert_eq!(bits.per_sector[(1 + set_in_byte) as usize], 1);
+    assert_eq!(bits.by_position[&(3, 2)], 1);
+    assert_eq!(
+        bits.by_position
+            .range((5, 8)..(5, 16))
+            .map(|(_, n)| n)
+            .sum::<u64>(),
+        set_in_byte
+    );
+    assert_eq!(bits.by_position.len() as u64, 1 + set_in_byte);
+    assert_eq!(bits.by_word()[3], 1);
+    assert_eq!(bits.by_word()[5], set_in_byte);
+    assert_eq!(bits.by_bit()[2], 1);
+    assert_eq!(
+        bits.by_bit().iter().skip(8).take(8).sum::<u64>(),
+        set_in_byte
+    );
+    assert_eq!(bits.cleared + bits.set, bits.flipped_bits());
+
+    let mut totals = FileStats::new();
+    totals.add(&stats);
+    totals.add(&stats);
+    assert_eq!(totals.bit_errors().flipped_bits(), 2 * bits.flipped_bits());
+    assert_eq!(totals.bit_errors().by_position[&(3, 2)], 2);
+
+    let json = serde_json::to_string(bits).unwrap();
+    assert_eq!(&serde_json::from_str::<BitErrors>(&json).unwrap(), bits);
+}
+
+#[test]
+fn sector_overwritten_records_source_file() {
+    let mut stats = FileStats::new();
+    let pattern = Pattern::default();
+    for offset in [12 * GIB, 12 * GIB + 512, 13 * GIB + 4096] {
+        let mut sector = [0u8; SECTOR_SIZE];
+        pattern.fill_sector(&mut sector, offset);
+        check_sector(&sector, 7 * GIB, &pattern, &mut stats);
+    }
+    assert_counts(&stats, 0, 0, 0, 3);
+    let by: Vec<(i64, u64)> = stats
+        .overwritten_by()
+        .iter()
+        .map(|(&k, &v)| (k, v))
+        .collect();
+    assert_eq!(by, vec![(12, 2), (13, 1)]);
+}
+
 // ---- test for check_buffer (some secs in a row) ----
 
 #[test]
@@ -340,10 +939,14 @@ fn buffer_three_sectors_all_ok() {
     buf.extend_from_slice(&s1);
     buf.extend_from_slice(&s2);
 
-    let new_off = check_buffer(&buf, buf.len(), expected_offset, &mut stats);
+    let new_off = check_buffer(
+        &buf,
+        buf.len(),
+        expected_offset,
+        &Pattern::default(),
+        &mut stats,
+    );
 
     assert_eq!(new_off, 1536);
     assert_counts(&stats, 3, 0, 0, 0);
 }
-
-// ---- tests for check_buffer ----
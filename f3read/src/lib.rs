@@ -1,11 +1,19 @@
 // f3read/src/lib.rs
 
-use std::{io::Result, time::Instant};
+use std::{io::Result, path::PathBuf, time::Instant};
 
 use f3core::{
+    cli::ReadArgs,
+    dashboard::{self, Event},
+    file_fill::MARKER_SPACING,
     flow::Flow,
-    utils::{SECTOR_SIZE, adjust_unit},
-    verify::FileStats,
+    interrupt::interrupted,
+    manifest::ManifestIssue,
+    pattern::PatternHint,
+    report::{FileResult, ReadSummary, SampleEstimate},
+    state::{ReadState, read_state_path},
+    utils::{adjust_unit, pr_time_str},
+    verify::{BitErrors, FileStats, check_markers, detect_pattern, sample_file},
 };
 
 pub fn pr_avg_speed(avg_speed: f64) {
@@ -13,56 +21,267 @@ pub fn pr_avg_speed(avg_speed: f64) {
     println!("Average speed: {:.2} {}/s", size, unit);
 }
 
+/// Everything `iterate_files` needs to know besides the files themselves.
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    pub dev_path: String,
+    pub start_at: i64,
+    pub end_at: i64,
+    pub hint: PatternHint,
+    pub max_read_rate: i64,
+    pub show_progress: bool,
+    /// Files of a quick run: only marker sectors are checked.
+    pub quick: bool,
+    /// Sectors checked per file, 0 = read whole files.
+    pub sample: usize,
+    pub sample_seed: u64,
+    /// Where the state is checkpointed, `None` = not at all.
+    pub state_file: Option<PathBuf>,
+}
+
+impl From<&ReadArgs> for ReadOptions {
+    fn from(args: &ReadArgs) -> Self {
+        ReadOptions {
+            dev_path: args.common.dev_path.clone(),
+            start_at: args.common.start_at,
+            end_at: args.common.end_at,
+            hint: args.pattern_hint(),
+            max_read_rate: args.max_read_rate,
+            show_progress: args.common.show_progress,
+            quick: args.quick,
+            sample: args.sample,
+            sample_seed: args.sample_seed,
+            state_file: Some(args.state_file()),
+        }
+    }
+}
+
+/// Validate `files`, skipping those already completed in `state`, which is
+/// checkpointed to `opts.state_file` after every file so that an interrupted
+/// run can be resumed.
+///
+/// When interrupted (see `f3core::interrupt`), the file being read is counted
+/// with what was read so far, but not checkpointed, and the state file is kept.
+///
+/// With `opts.sample`, only that many random sectors of each file are checked
+/// and the totals are extrapolated; nothing is checkpointed then. With
+/// `opts.quick`, only the markers are checked and each stands for MARKER_SPACING.
 pub fn iterate_files(
-    dev_path: &str,
+    opts: &ReadOptions,
     files: Vec<i64>,
-    start_at: i64,
-    // end_at: i64,
-    max_read_rate: i64,
-    show_progress: bool,
-) -> Result<()> {
-    let mut flow = Flow::new(get_total_size(&files), max_read_rate, show_progress);
-
-    let (mut tot_ok, mut tot_corrupted, mut tot_changed, mut tot_overwritten, mut tot_size) =
-        (0, 0, 0, 0, 0);
+    manifest_issues: &[ManifestIssue],
+    state: &mut ReadState,
+) -> Result<ReadSummary> {
+    let dev_path = opts.dev_path.as_str();
+    let hint = &opts.hint;
+    let start_at = opts.start_at;
+
+    let sector_size = hint.fallback().sector_size;
+    let total_size = get_total_size(dev_path, &files);
+    let spacing = if opts.quick {
+        MARKER_SPACING
+    } else {
+        sector_size as u64
+    };
+    if opts.quick {
+        println!("Quick mode: checking one marker sector per MiB");
+    }
+    let mut flow =
+        Flow::new(total_size, opts.max_read_rate, opts.show_progress).with_block_size(sector_size);
+    if !state.completed.is_empty() {
+        flow.restore(&state.flow);
+        println!(
+            "Resuming: {} file(s) already verified",
+            state.completed.len()
+        );
+    }
+
+    let mut summary = ReadSummary {
+        manifest_issues: manifest_issues.iter().map(|i| i.to_string()).collect(),
+        quick: opts.quick,
+        sector_size,
+        ..Default::default()
+    };
     let and_read_all = true;
     let mut or_missing_file = false;
     let mut number = start_at;
 
-    println!("                  SECTORS       ok/corrupted/changed/overwritten");
+    println!("                  SECTORS       ok/corrupted/changed/overwritten/stale/zeroed/ones");
 
+    let prior_ms = state.elapsed_ms;
     let start_time = Instant::now();
+    let mut state_file = opts.state_file.as_deref();
+    state.hint = *hint;
+    state.quick = opts.quick;
+
+    dashboard::emit(Event::Start {
+        tool: "read",
+        path: dev_path.to_string(),
+        total_bytes: total_size,
+        files: files.clone(),
+    });
 
     for file_num in files {
+        if interrupted() {
+            break;
+        }
+        dashboard::emit(Event::FileStarted(file_num));
         or_missing_file = or_missing_file || file_num != number;
 
         while number < file_num {
             println!("Missing file: {}.h2w", number);
+            summary.missing.push(number);
             number += 1;
         }
         number += 1;
 
-        let mut stats = FileStats::new();
-        stats.validate_file(dev_path, file_num as i32, &mut flow)?;
+        let stats = match state.stats_of(file_num) {
+            Some(stats) => {
+                let val_str = format!("Validating file: {}.h2w ... ", file_num);
+                println!("{}", val_str);
+                stats.print_status(&val_str);
+                stats.clone()
+            }
+            None if opts.sample > 0 => {
+                let full_fn = format!("{}{}.h2w", dev_path, file_num);
+                let pattern = detect_pattern(&full_fn, hint).unwrap_or_else(|| hint.fallback());
+
+                let val_str = format!("Sampling file: {}.h2w ... ", file_num);
+                println!("{}", val_str);
+                let stats = sample_file(
+                    dev_path,
+                    file_num,
+                    opts.sample,
+                    spacing,
+                    &pattern,
+                    opts.sample_seed,
+                )?;
+                stats.print_status(&val_str);
+                stats
+            }
+            None => {
+                let full_fn = format!("{}{}.h2w", dev_path, file_num);
+                let pattern = detect_pattern(&full_fn, hint).unwrap_or_else(|| hint.fallback());
+
+                let stats = if opts.quick {
+                    let val_str = format!("Validating file: {}.h2w ... ", file_num);
+                    println!("{}", val_str);
+                    let stats = check_markers(dev_path, file_num, &pattern)?;
+                    stats.print_status(&val_str);
+                    stats
+                } else {
+                    let mut stats = FileStats::new();
+                    stats.validate_file(dev_path, file_num as i32, &pattern, &mut flow)?;
+                    stats
+                };
+                if interrupted() {
+                    summary.totals.add(&stats);
+                    summary.files.push(FileResult {
+                        number: file_num,
+                        stats,
+                    });
+                    break;
+                }
+
+                state.record(file_num, stats.clone());
+                state.flow = flow.state();
+                state.elapsed_ms = prior_ms + start_time.elapsed().as_millis() as u64;
+                // Warn once and go on without it
+                if let Some(file) = state_file
+                    && let Err(e) = state.save(file)
+                {
+                    eprintln!(
+                        "Warning: Cannot write {}: {}, --resume won't be available",
+                        file.display(),
+                        e
+                    );
+                    state_file = None;
+                }
+                stats
+            }
+        };
 
-        tot_ok += stats.secs_ok();
-        tot_corrupted += stats.secs_corrupted();
-        tot_changed += stats.secs_changed();
-        tot_overwritten += stats.secs_overwritten();
-        tot_size += stats.bytes_read();
+        if stats.secs_overwritten() > 0 {
+            let bytes_per_sector = if opts.sample > 0 {
+                let len = std::fs::metadata(format!("{}{}.h2w", dev_path, file_num))
+                    .map_or(0, |m| m.len());
+                len / (stats.secs_ok() + stats.secs_lost()).max(1)
+            } else {
+                spacing
+            };
+            report_overwritten(file_num, &stats, bytes_per_sector);
+        }
+
+        dashboard::emit(Event::FileDone {
+            number: file_num,
+            stats: Some(stats.clone()),
+        });
+        summary.totals.add(&stats);
+        summary.files.push(FileResult {
+            number: file_num,
+            stats,
+        });
     }
-    assert!(
-        tot_size == SECTOR_SIZE as u64 * (tot_ok + tot_corrupted + tot_changed + tot_overwritten)
-    );
+    if opts.sample > 0 {
+        summary.estimate = Some(SampleEstimate::new(&summary.totals, total_size));
+    }
+    let tot = &summary.totals;
+    assert!(tot.bytes_read() == sector_size as u64 * (tot.secs_ok() + tot.secs_lost()));
+    // Sampled counts and markers stand for the whole run, see `scaled_bytes`
 
     // Notice that not reporting `missing' files after the last file
     // in @files is important since @end_at could be very large.
+    // The manifest knows which of them were written though.
+    for issue in manifest_issues {
+        if let ManifestIssue::Missing(n) = issue
+            && !summary.missing.contains(n)
+        {
+            summary.missing.push(*n);
+        }
+    }
+    summary.missing.sort();
+
+    let was_interrupted = interrupted();
+    if was_interrupted {
+        println!("\nWARNING: Interrupted, the results are partial");
+    }
+
+    report("\n  Data OK:", summary.scaled_bytes(tot.secs_ok()));
+    report("Data LOST:", summary.scaled_bytes(tot.secs_lost()));
+    report(
+        "\t       Corrupted:",
+        summary.scaled_bytes(tot.secs_corrupted()),
+    );
+    report(
+        "\tSlightly changed:",
+        summary.scaled_bytes(tot.secs_changed()),
+    );
+    report(
+        "\t     Overwritten:",
+        summary.scaled_bytes(tot.secs_overwritten()),
+    );
+    report(
+        "\t           Stale:",
+        summary.scaled_bytes(tot.secs_stale()),
+    );
+    report(
+        "\t     Zero-filled:",
+        summary.scaled_bytes(tot.secs_zeroed()),
+    );
+    report("\t     Ones-filled:", summary.scaled_bytes(tot.secs_ones()));
+    if tot.bit_errors().flipped_bits() > 0 {
+        report_bit_errors(tot.bit_errors());
+    }
 
-    report("\n  Data OK:", tot_ok);
-    report("Data LOST:", tot_corrupted + tot_changed + tot_overwritten);
-    report("\t       Corrupted:", tot_corrupted);
-    report("\tSlightly changed:", tot_changed);
-    report("\t     Overwritten:", tot_overwritten);
+    if let Some(est) = &summary.estimate {
+        let (low, low_unit) = adjust_unit(est.lost_low as f64);
+        let (high, high_unit) = adjust_unit(est.lost_high as f64);
+        println!(
+            "Estimated from {} sampled sectors (seed {}), Data LOST is between \
+             {:.2} {} and {:.2} {} with 95% confidence\n",
+            est.samples, opts.sample_seed, low, low_unit, high, high_unit
+        );
+    }
 
     if or_missing_file {
         println!(
@@ -71,32 +290,64 @@ pub fn iterate_files(
             number
         );
     }
+    if !manifest_issues.is_empty() {
+        println!("WARNING: The files don't match the manifest:");
+        for issue in manifest_issues {
+            println!("\t{}", issue);
+        }
+        println!();
+    }
     if !and_read_all {
         println!("WARNING: Not all data was read due to I/O error(s)\n");
     }
 
     // Reading speed
-    if flow.has_enough_measurements() {
-        pr_avg_speed(flow.get_avg_speed());
+    summary.speeds = flow.speed_samples().to_vec();
+    summary.elapsed_ms = prior_ms + start_time.elapsed().as_millis() as u64;
+    if opts.sample > 0 || opts.quick {
+        println!(
+            "Checking took {}",
+            pr_time_str(summary.elapsed_ms as f64 / 1000.0)
+        );
+    } else if flow.has_enough_measurements() {
+        summary.avg_speed = flow.get_avg_speed();
+        pr_avg_speed(summary.avg_speed);
     } else {
         // If the drive is too fast for the measurements above,
         // try a coarse approximation of the reading speed.
 
-        let total_time_ms = start_time.elapsed().as_millis() as u64;
-        if total_time_ms > 0 {
-            pr_avg_speed(flow.get_avg_speed_given_time(total_time_ms));
+        if summary.elapsed_ms > 0 {
+            summary.avg_speed = flow.get_avg_speed_given_time(summary.elapsed_ms);
+            pr_avg_speed(summary.avg_speed);
         } else {
             println!("Reading speed not available")
         }
     }
 
-    Ok(())
+    if opts.sample > 0 {
+        // Sampling doesn't touch the state of a full run
+    } else if was_interrupted {
+        match &opts.state_file {
+            Some(file) if *file != read_state_path(dev_path) => println!(
+                "Run nf3_read --resume --state {} {} to continue",
+                file.display(),
+                dev_path
+            ),
+            _ => println!("Run nf3_read --resume {} to continue", dev_path),
+        }
+    } else if let Some(file) = &opts.state_file
+        && let Err(e) = ReadState::remove(file)
+    {
+        eprintln!("Error: Failed to delete {}: {}", file.display(), e);
+    }
+
+    Ok(summary)
 }
 
-fn get_total_size(files: &Vec<i64>) -> u64 {
+fn get_total_size(dev_path: &str, files: &Vec<i64>) -> u64 {
     let mut total_size = 0;
     for file_num in files {
-        let file = format!("{}{}", file_num, ".h2w");
+        let file = format!("{}{}.h2w", dev_path, file_num);
         if let Ok(metadata) = std::fs::metadata(&file) {
             total_size += metadata.len();
         } else {
@@ -106,8 +357,88 @@ fn get_total_size(files: &Vec<i64>) -> u64 {
     total_size
 }
 
-fn report(prefix: &str, i: u64) {
-    let (size, unit) = adjust_unit((i * SECTOR_SIZE as u64) as f64);
+/// Where the overwritten sectors of `number` came from, e.g.
+/// "7.h2w: 1.00 GB overwritten by data from 12.h2w–13.h2w".
+fn report_overwritten(number: i64, stats: &FileStats, bytes_per_sector: u64) {
+    // Contiguous runs of source files
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+    for &source in stats.overwritten_by().keys() {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == source => *last = source,
+            _ => ranges.push((source, source)),
+        }
+    }
+    let sources = ranges
+        .iter()
+        .map(|&(first, last)| {
+            if first == last {
+                format!("{}.h2w", first)
+            } else {
+                format!("{}.h2w–{}.h2w", first, last)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let (size, unit) = adjust_unit((stats.secs_overwritten() * bytes_per_sector) as f64);
+    println!(
+        "{}.h2w: {:.2} {} overwritten by data from {}",
+        number, size, unit, sources
+    );
+}
+
+/// Histogram of the flipped bits of changed sectors and a guess at the cause.
+fn report_bit_errors(bits: &BitErrors) {
+    // "label (count)" of the largest counts first
+    let top = |counts: Vec<(String, u64)>| {
+        let mut nonzero: Vec<(String, u64)> = counts.into_iter().filter(|c| c.1 > 0).collect();
+        nonzero.sort_by_key(|c| std::cmp::Reverse(c.1));
+        nonzero
+            .iter()
+            .take(5)
+            .map(|(label, n)| format!("{} ({})", label, n))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let indexed = |counts: &[u64]| {
+        counts
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| (i.to_string(), n))
+            .collect::<Vec<_>>()
+    };
+    let sectors: u64 = bits.per_sector.iter().sum();
+    let flipped = bits.flipped_bits();
+    let max_position = bits.by_position.values().copied().max().unwrap_or_default();
+
+    println!(
+        "\nBit errors in changed sectors: {} bits ({} 0->1, {} 1->0)",
+        flipped, bits.set, bits.cleared
+    );
+    println!("\tBits per sector: {}", top(indexed(&bits.per_sector)));
+    println!("\t   Top words: {}", top(indexed(&bits.by_word())));
+    println!("\t    Top bits: {}", top(indexed(&bits.by_bit())));
+    println!(
+        "\t   Top word:bit: {}",
+        top(bits
+            .by_position
+            .iter()
+            .map(|(&(word, bit), &n)| (format!("{}:{}", word, bit), n))
+            .collect())
+    );
+
+    let guess = if bits.per_sector.iter().skip(2).all(|&n| n == 0) {
+        "single-bit flips, typical of aging NAND"
+    } else if sectors > 1 && max_position * 2 >= flipped {
+        "the same bit keeps flipping, likely a systematic controller bug"
+    } else {
+        "multi-bit errors, e.g. stuck bytes"
+    };
+    println!("\tLooks like: {}\n", guess);
+}
+
+fn report(prefix: &str, bytes: u64) {
+    let (size, unit) = adjust_unit(bytes as f64);
     println!("{}: {} {}", prefix, size, unit);
 }
 
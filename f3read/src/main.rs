@@ -1,3 +1,6 @@
+// --- std ---
+use std::{io, process};
+
 // --- external crates ---
 use clap::Parser;
 // use simple_log::{Level, LogConfigBuilder};
@@ -5,13 +8,23 @@ use clap::Parser;
 // --- internal modules ---
 use f3core::{
     cli::ReadArgs,
-    utils::{self, adjust_dev_path, ls_my_files, parse_dev_and_num},
+    dashboard,
+    device::DeviceInfo,
+    history::{self, HistoryEntry},
+    interrupt::{self, EXIT_INTERRUPTED},
+    lock::RunLock,
+    manifest::{MANIFEST_NAME, Manifest},
+    report::RunReport,
+    speed,
+    state::ReadState,
+    utils::{self, GIB, adjust_dev_path, ls_my_files, parse_dev_and_num},
 };
 use f3read::*;
 
 // Before running f3read, make sure your device is mounted!!
 fn main() {
     let mut args = ReadArgs::parse();
+    interrupt::install_handler();
 
     // Validate the arguments
     args.validate_args();
@@ -27,6 +40,54 @@ fn main() {
     }
 
     adjust_dev_path(&mut args.common.dev_path);
+    let _lock = match RunLock::acquire_for_reading(
+        &args.common.dev_path,
+        "nf3_read",
+        args.common.wait_lock,
+    ) {
+        Ok(lock) => Some(lock),
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+            ) =>
+        {
+            eprintln!("Error: {}", e);
+            if e.kind() == io::ErrorKind::WouldBlock {
+                eprintln!("Pass --wait-lock to wait for it to finish");
+            }
+            process::exit(1);
+        }
+        // Reading doesn't need the lock, e.g. on a drive mounted read-only
+        Err(e) => {
+            eprintln!("Warning: Cannot lock {}: {}", args.common.dev_path, e);
+            None
+        }
+    };
+
+    let state_file = args.state_file();
+    let mut state = if args.resume {
+        match ReadState::load(&state_file) {
+            Ok(Some(state)) => {
+                args.common.start_at = state.start_at;
+                args.common.end_at = state.end_at;
+                state
+            }
+            Ok(None) => {
+                eprintln!(
+                    "Error: Nothing to resume, {} not found",
+                    state_file.display()
+                );
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: Failed to load {}: {}", state_file.display(), e);
+                process::exit(1);
+            }
+        }
+    } else {
+        ReadState::new(args.common.start_at, args.common.end_at)
+    };
 
     let files = ls_my_files(
         &args.common.dev_path,
@@ -34,15 +95,102 @@ fn main() {
         args.common.end_at,
     );
 
-    match iterate_files(
-        &args.common.dev_path,
-        files,
-        args.common.start_at,
-        // args.common.end_at,
-        args.max_read_rate,
-        args.common.show_progress,
-    ) {
-        Ok(_) => log::info!("Finished reading files successfully."),
-        Err(e) => log::error!("Error reading files: {}", e),
+    let mut opts = ReadOptions::from(&args);
+    let mut report = RunReport::new(&args.common.dev_path);
+    report.describe_target(DeviceInfo::probe(&args.common.dev_path));
+    let issues = match Manifest::load(&args.common.dev_path) {
+        Ok(Some(manifest)) => {
+            report.run_id = Some(manifest.run_id.clone());
+            report.write = Some(manifest.write_summary());
+            println!(
+                "Manifest: run {} written on {} ({} files)",
+                manifest.run_id,
+                manifest.host,
+                manifest.files.len()
+            );
+            if manifest.file_size != GIB {
+                eprintln!(
+                    "Warning: files were written with {} byte files, this build expects {}",
+                    manifest.file_size, GIB
+                );
+            }
+            if let Some(size) = opts.hint.sector_size
+                && size != manifest.sector_size
+            {
+                eprintln!(
+                    "Warning: files were written with {} byte sectors, reading with {}",
+                    manifest.sector_size, size
+                );
+            }
+            manifest.apply_to(&mut opts.hint);
+            opts.quick |= manifest.quick;
+            manifest.check(
+                &args.common.dev_path,
+                &files,
+                args.common.start_at,
+                args.common.end_at,
+            )
+        }
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            eprintln!("Error: Failed to load {}: {}", MANIFEST_NAME, e);
+            Vec::new()
+        }
+    };
+
+    if args.resume && (state.hint != opts.hint || state.quick != opts.quick) {
+        eprintln!(
+            "Error: The interrupted run was checked with {}",
+            state.describe_options()
+        );
+        eprintln!("Pass the same options to --resume, or start over without it");
+        process::exit(1);
+    }
+
+    if args.common.tui
+        && let Err(e) = dashboard::start()
+    {
+        eprintln!("Warning: Cannot show the dashboard: {}", e);
+    }
+    match iterate_files(&opts, files, &issues, &mut state) {
+        Ok(summary) => report.read = Some(summary),
+        Err(e) => eprintln!("Error reading files: {}", e),
+    }
+    report.interrupted = interrupt::interrupted();
+    dashboard::stop();
+
+    if let Some(prefix) = &args.common.speed_chart {
+        let write = report.write.as_ref().map_or(&[][..], |w| &w.speeds[..]);
+        let read = report.read.as_ref().map_or(&[][..], |r| &r.speeds[..]);
+        if let Err(e) = speed::export(prefix, write, read) {
+            eprintln!("Error: Failed to write speed chart {}: {}", prefix, e);
+        }
+    }
+    if let Some(file) = &args.common.report
+        && let Err(e) = report.save(file)
+    {
+        eprintln!("Error: Failed to write report {}: {}", file, e);
+    }
+    if let Some(file) = &args.junit
+        && let Err(e) = report.save_junit(file)
+    {
+        eprintln!("Error: Failed to write JUnit report {}: {}", file, e);
+    }
+    if let Some(file) = &args.common.html
+        && let Err(e) = report.save_html(file)
+    {
+        eprintln!("Error: Failed to write HTML report {}: {}", file, e);
+    }
+    if let Some(file) = args.common.history_file()
+        && let Err(e) = history::append(&file, &HistoryEntry::new("read", &report))
+    {
+        eprintln!(
+            "Error: Failed to record the run in {}: {}",
+            file.display(),
+            e
+        );
+    }
+    if report.interrupted {
+        process::exit(EXIT_INTERRUPTED);
     }
 }
@@ -1 +1,131 @@
+// f3read/src/tests.rs
 
+use crate::*;
+
+use f3core::{
+    junit,
+    manifest::Manifest,
+    pattern::{Pattern, PatternHint},
+    report::RunReport,
+    state::ReadState,
+    tests::helpers::write_h2w,
+    utils::SECTOR_SIZE,
+};
+
+fn options(dir: &str) -> ReadOptions {
+    ReadOptions {
+        dev_path: dir.to_string(),
+        start_at: 1,
+        end_at: 0,
+        hint: PatternHint::default(),
+        max_read_rate: 0,
+        show_progress: false,
+        quick: false,
+        sample: 0,
+        sample_seed: 1,
+        state_file: Some(format!("{}state.json", dir).into()),
+    }
+}
+
+#[test]
+fn resume_skips_completed_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    write_h2w(&p, 1, 4, &Pattern::default());
+    write_h2w(&p, 2, 4, &Pattern::default());
+
+    let opts = options(&p);
+    let mut state = ReadState::new(1, 0);
+    iterate_files(&opts, vec![1, 2], &[], &mut state).unwrap();
+    assert_eq!(state.completed.len(), 2);
+    assert!(
+        ReadState::load(opts.state_file.as_deref().unwrap())
+            .unwrap()
+            .is_none(),
+        "state left behind"
+    );
+
+    // Pretend the run was interrupted after 1.h2w, then break 1.h2w:
+    // it must not be read again.
+    let mut resumed = ReadState::new(1, 0);
+    resumed.record(1, state.completed[0].stats.clone());
+    std::fs::write(format!("{}1.h2w", p), vec![0u8; 4 * SECTOR_SIZE]).unwrap();
+
+    iterate_files(&opts, vec![1, 2], &[], &mut resumed).unwrap();
+    assert_eq!(resumed.completed, state.completed);
+}
+
+#[test]
+fn sample_mode_extrapolates_without_state() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    write_h2w(&p, 1, 64, &Pattern::default());
+    write_h2w(&p, 2, 64, &Pattern::default());
+    // Second half of 2.h2w lost
+    let mut data = std::fs::read(format!("{}2.h2w", p)).unwrap();
+    data[32 * SECTOR_SIZE..].fill(0);
+    std::fs::write(format!("{}2.h2w", p), data).unwrap();
+
+    let mut opts = options(&p);
+    opts.sample = 100;
+    let mut state = ReadState::new(1, 0);
+    let summary = iterate_files(&opts, vec![1, 2], &[], &mut state).unwrap();
+    assert!(state.completed.is_empty());
+    assert!(
+        ReadState::load(opts.state_file.as_deref().unwrap())
+            .unwrap()
+            .is_none()
+    );
+
+    let tot = &summary.totals;
+    assert_eq!(tot.secs_ok() + tot.secs_lost(), 200);
+    let est = summary.estimate.unwrap();
+    assert_eq!(est.total_bytes, 128 * SECTOR_SIZE as u64);
+    // A quarter of the data is lost
+    let quarter = est.total_bytes / 4;
+    assert!(est.lost_low < quarter && quarter < est.lost_high);
+    assert!(est.lost_low <= est.lost_bytes && est.lost_bytes <= est.lost_high);
+
+    // Same seed, same sectors
+    let again = iterate_files(&opts, vec![1, 2], &[], &mut ReadState::new(1, 0)).unwrap();
+    assert_eq!(again.totals, summary.totals);
+}
+
+#[test]
+fn overwritten_sectors_name_their_source_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    // 1.h2w holds the data of 3.h2w, as on a drive that wrapped around
+    write_h2w(&p, 3, 4, &Pattern::default());
+    std::fs::copy(format!("{}3.h2w", p), format!("{}1.h2w", p)).unwrap();
+
+    let summary = iterate_files(&options(&p), vec![1, 3], &[], &mut ReadState::new(1, 0)).unwrap();
+    let first = &summary.files[0].stats;
+    assert_eq!(first.secs_overwritten(), 4);
+    assert_eq!(first.overwritten_by().get(&3), Some(&4));
+    assert!(summary.files[1].stats.overwritten_by().is_empty());
+}
+
+#[test]
+fn files_missing_from_the_manifest_fail_the_junit_suite() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    let pattern = Pattern::default();
+    write_h2w(&p, 1, 4, &pattern);
+
+    // The last two files of the run vanished
+    let mut manifest = Manifest::new(&pattern);
+    for n in 1..=3 {
+        manifest.record_file(n, 4 * SECTOR_SIZE as u64);
+    }
+    let issues = manifest.check(&p, &[1], 1, 0);
+
+    let mut report = RunReport::new(&p);
+    report.read =
+        Some(iterate_files(&options(&p), vec![1], &issues, &mut ReadState::new(1, 0)).unwrap());
+    assert_eq!(report.read.as_ref().unwrap().missing, [2, 3]);
+
+    let xml = junit::render(&report);
+    assert!(xml.contains("tests=\"3\" failures=\"2\""));
+    assert!(xml.contains("type=\"Missing\" message=\"3.h2w is missing\""));
+}
@@ -1,8 +1,8 @@
 // f3write/src/lib.rs
 
 use std::{
-    fs::OpenOptions,
-    io::{self, Result, Write},
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind, Result, Write},
     process,
     time::Instant,
 };
@@ -14,10 +14,19 @@ use crossterm::{
 };
 
 use f3core::{
-    file_fill::fill_file,
+    dashboard::{self, Event},
+    file_fill::{MARKER_SPACING, fill_file, fill_markers},
     flow::Flow,
-    utils::{GIB, adjust_unit, pr_time_str},
+    interrupt::interrupted,
+    manifest::{MANIFEST_NAME, Manifest},
+    pattern::{Pattern, PatternHint},
+    probe::{CacheSize, PROBE_BLOCK_SIZE, probe_cache_size},
+    report::{ReadSummary, WriteSummary},
+    state::{ReadState, WRITE_STATE_NAME, WriteState},
+    utils::{GIB, adjust_unit, fadvise_dontneed, ls_my_files, new_run_seed, pr_time_str},
+    verify::{FileStats, check_markers, spot_check},
 };
+use f3read::{ReadOptions, iterate_files};
 
 #[cfg(windows)]
 pub fn get_freespace(path: &str) -> std::io::Result<u64> {
@@ -70,12 +79,50 @@ pub fn print_freespace(path: &str) {
     }
 }
 
+/// Measure the write-back cache of the device holding `path` with a scratch
+/// file of up to `max_mib` MiB, which is removed afterwards.
+pub fn probe_cache(path: &str, max_mib: u64) -> Result<CacheSize> {
+    let full = format!("{}nf3-probe.tmp", path);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open(&full)?;
+
+    println!("Probing write-back cache (up to {} MiB)...", max_mib);
+    let res = probe_cache_size(&mut file, PROBE_BLOCK_SIZE, max_mib << 20);
+    drop(file);
+    if let Err(e) = std::fs::remove_file(&full) {
+        eprintln!("Error: Failed to delete {}: {}", full, e);
+    }
+
+    let res = res?;
+    match res {
+        CacheSize::Detected { bytes } => {
+            let (size, unit) = adjust_unit(bytes as f64);
+            println!(
+                "Write-back cache: ~{:.2} {} (data lost beyond it)",
+                size, unit
+            );
+        }
+        CacheSize::NotDetected { tested } => {
+            let (size, unit) = adjust_unit(tested as f64);
+            println!("Write-back cache: no data loss up to {:.2} {}", size, unit);
+        }
+    }
+    Ok(res)
+}
+
 /// Create (or truncate) the file `<path>/<number>.h2w`, fill it completely
-/// (calling `fill_file`), and return `true` if ENOSPC (no space left) was encountered.
+/// (calling `fill_file`, or `fill_markers` when `quick`), and return `true` if
+/// ENOSPC (no space left) was encountered.
 pub fn create_and_fill_file(
     path: &str,
     number: i64,
     size: u64,
+    pattern: &Pattern,
+    quick: bool,
     _has_suggested_max_write_rate: bool,
     fw: &mut Flow,
 ) -> Result<()> {
@@ -90,11 +137,12 @@ pub fn create_and_fill_file(
         .truncate(true)
         .open(&full)
     {
-        Ok(mut file) => fill_file(&mut file, number, size, fw),
+        Ok(mut file) if quick => fill_markers(&mut file, number, size, pattern, fw),
+        Ok(mut file) => fill_file(&mut file, number, size, pattern, fw),
         Err(e) if e.raw_os_error() == Some(28) => {
             // ENOSPC
             println!("No space left.");
-            Ok(())
+            Err(e)
         }
         Err(e) => {
             eprintln!("Error creating file {}: {}", full, e);
@@ -103,66 +151,473 @@ pub fn create_and_fill_file(
     }
 }
 
+/// Bytes to write and the last file to write them to, given `free` bytes:
+/// all of `start_at..=end_at` if they fit, otherwise as many files as the
+/// free space allows, the last one possibly partial. `end_at` 0 = no limit.
+pub fn plan_range(free: u64, start_at: i64, end_at: i64) -> (u64, i64) {
+    let count = end_at - start_at + 1;
+    if count > 0 && (count as u64) <= (free >> 30) {
+        ((count as u64) << 30, end_at)
+    } else {
+        (free, start_at + (free >> 30) as i64)
+    }
+}
+
+/// Bytes to write and the files to write them to, with their sizes, given
+/// `free` bytes, see `plan_range`.
+pub fn plan_files(free: u64, start_at: i64, end_at: i64) -> (u64, Vec<(i64, u64)>) {
+    let (bytes, end_at) = plan_range(free, start_at, end_at);
+
+    let mut create = Vec::new();
+    let mut left = bytes;
+    for n in start_at..=end_at {
+        if left == 0 {
+            break;
+        }
+        create.push((n, left.min(GIB)));
+        left -= left.min(GIB);
+    }
+    (bytes, create)
+}
+
+/// What `nf3_write --dry-run` would do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WritePlan {
+    /// Free space once the old files are deleted.
+    pub free: u64,
+    /// Old files in the range, with their sizes.
+    pub delete: Vec<(i64, u64)>,
+    /// Files to write, with their planned sizes.
+    pub create: Vec<(i64, u64)>,
+    pub bytes: u64,
+}
+
+/// Plan a run over `start_at..=end_at` in `path` like `fill_fs` does, without
+/// touching the disk.
+pub fn plan_write(path: &str, start_at: i64, end_at: i64) -> Result<WritePlan> {
+    let delete: Vec<(i64, u64)> = ls_my_files(path, start_at, end_at)
+        .into_iter()
+        .map(|n| {
+            let size = std::fs::metadata(format!("{}{}.h2w", path, n)).map_or(0, |m| m.len());
+            (n, size)
+        })
+        .collect();
+    let free = get_freespace(path)? + delete.iter().map(|(_, size)| size).sum::<u64>();
+    let (bytes, create) = plan_files(free, start_at, end_at);
+    Ok(WritePlan {
+        free,
+        delete,
+        create,
+        bytes,
+    })
+}
+
+pub fn print_plan(plan: &WritePlan) {
+    let size = |bytes: u64| {
+        let (s, unit) = adjust_unit(bytes as f64);
+        format!("{:.2} {}", s, unit)
+    };
+    println!("Dry run, nothing is written or deleted");
+    println!("Free space (after deleting old files): {}", size(plan.free));
+    for (n, bytes) in &plan.delete {
+        println!("Would delete: {}.h2w ({})", n, size(*bytes));
+    }
+    for (n, bytes) in &plan.create {
+        println!("Would create: {}.h2w ({})", n, size(*bytes));
+    }
+    if plan.create.is_empty() {
+        println!("Nothing to write: no free space available on the device");
+    } else {
+        println!(
+            "Would write {} in {} file(s)",
+            size(plan.bytes),
+            plan.create.len()
+        );
+    }
+}
+
 /// Top‐level: fill the filesystem at `path` with numbered .h2w files from `start_at`
-/// through `*end_at`, respecting available free space and optional rate/progress.
-/// Adjusts `*end_at` if free space is smaller than requested file count.
+/// through `end_at` of `state`, respecting available free space and optional rate/progress.
+/// Adjusts `end_at` if free space is smaller than requested file count.
+///
+/// With `resume`, `state` comes from an interrupted run: the possibly partial
+/// file after the last completed one is verified (and rewritten if needed)
+/// and the run continues with the totals of the interrupted one.
+///
+/// When interrupted (see `f3core::interrupt`), the current file is synced,
+/// the state file is kept for `--resume` and a partial summary is returned.
+/// The state file is also kept when a write error other than a full drive
+/// stops the run; `write_error` is set in the summary.
 pub fn fill_fs(
     path: &str,
-    start_at: i64,
-    end_at: &mut i64,
-    max_write_rate: i64,
+    state: &mut WriteState,
+    resume: bool,
     show_progress: bool,
-) -> Result<()> {
-    let mut free = get_freespace(path)?;
-    if free == 0 {
-        eprintln!("Error: no free space available on the device.");
-        process::exit(1);
-    }
+) -> Result<WriteSummary> {
+    let sector_size = state.pattern.sector_size;
+    let mut flow = Flow::new(0, state.max_write_rate, show_progress).with_block_size(sector_size);
+    let mut manifest;
 
-    let count = *end_at - start_at + 1;
-    if count > 0 && (count as u64) <= (free >> 30) {
-        free = (count as u64) << 30;
+    if resume {
+        manifest = match Manifest::load(path) {
+            Ok(Some(m)) if m.run_id == state.run_id => m,
+            _ => {
+                eprintln!(
+                    "Warning: {} doesn't match the run, recreating it",
+                    MANIFEST_NAME
+                );
+                let mut m = Manifest::new(&state.pattern);
+                m.run_id = state.run_id.clone();
+                m.quick = state.quick;
+                (m.start_at, m.end_at) = (state.start_at, state.end_at);
+                m
+            }
+        };
+        // A failed run recorded only the files it created
+        for n in state.start_at..=state.end_at {
+            manifest.record_file(n, GIB);
+        }
+        flow.restore(&state.flow);
+        println!(
+            "Resuming run {} at {}.h2w (files {} to {})",
+            state.run_id,
+            state.next_file(),
+            state.start_at,
+            state.end_at
+        );
+
+        let next = state.next_file();
+        if next <= state.end_at && verify_written_file(path, next, GIB, &state.pattern, state.quick)
+        {
+            println!("{}.h2w is complete, keeping it", next);
+            flow.skip(GIB);
+            state.last_completed = Some(next);
+        }
     } else {
-        *end_at = start_at + (free >> 30) as i64;
-    }
+        let mut free = get_freespace(path)?;
+        if free == 0 {
+            eprintln!("Error: no free space available on the device.");
+            process::exit(1);
+        }
+
+        (free, state.end_at) = plan_range(free, state.start_at, state.end_at);
+
+        let fs = adjust_unit(free as f64);
+        println!("Free space: {} {}", fs.0, fs.1);
+
+        // Written up front with the planned files, so its blocks are already
+        // allocated when the final sizes are recorded on a full drive.
+        manifest = Manifest::new(&state.pattern);
+        manifest.quick = state.quick;
+        (manifest.start_at, manifest.end_at) = (state.start_at, state.end_at);
+        state.run_id = manifest.run_id.clone();
+        for n in state.start_at..=state.end_at {
+            manifest.record_file(n, GIB);
+        }
+        if let Err(e) = manifest.save(path) {
+            eprintln!("Error: Failed to write {}: {}", MANIFEST_NAME, e);
+        }
 
-    let fs = adjust_unit(free as f64);
-    println!("Free space: {} {}", fs.0, fs.1);
+        flow = Flow::new(free, state.max_write_rate, show_progress).with_block_size(sector_size);
+        state.flow = flow.state();
+    }
 
-    let mut flow = Flow::new(free, max_write_rate, show_progress);
+    let pattern = state.pattern;
+    println!("Pattern: {}", pattern.kind.name());
+    if pattern.sector_size != Pattern::default().sector_size
+        || pattern.tolerance != Pattern::default().tolerance
+    {
+        println!(
+            "Sector size: {} bytes, tolerance: {} words",
+            pattern.sector_size, pattern.tolerance
+        );
+    }
+    if state.quick {
+        println!("Quick mode: one marker sector per MiB, the rest is only allocated");
+    }
+    if pattern.seed != 0 {
+        println!(
+            "Run seed: {} (pass --seed {} to nf3_read)",
+            pattern.seed, pattern.seed
+        );
+    }
 
+    let prior_ms = state.elapsed_ms;
     let start_time = Instant::now();
+    let elapsed_ms = || prior_ms + start_time.elapsed().as_millis() as u64;
 
-    for n in start_at..=*end_at {
-        let stop = create_and_fill_file(path, n, GIB, show_progress, &mut flow).is_err();
+    if let Err(e) = state.save(path) {
+        eprintln!("Error: Failed to write {}: {}", WRITE_STATE_NAME, e);
+    }
+
+    let mut rng = new_run_seed();
+    let mut fake_capacity_at = None;
+    let mut failed = false;
+
+    dashboard::emit(Event::Start {
+        tool: "write",
+        path: path.to_string(),
+        total_bytes: state.flow.total_size,
+        files: (state.start_at..=state.end_at).collect(),
+    });
+    for n in state.start_at..state.next_file() {
+        dashboard::emit(Event::FileDone {
+            number: n,
+            stats: None,
+        });
+    }
+
+    for n in state.next_file()..=state.end_at {
+        if interrupted() {
+            break;
+        }
+        dashboard::emit(Event::FileStarted(n));
+        let res = create_and_fill_file(
+            path,
+            n,
+            GIB,
+            &pattern,
+            state.quick,
+            show_progress,
+            &mut flow,
+        );
 
         execute!(io::stdout(), Clear(ClearType::CurrentLine), MoveToColumn(0),).unwrap();
 
-        if stop {
+        if let Err(e) = res {
+            // A full drive is the normal end of a run, the last file partial
+            failed = e.raw_os_error() != Some(libc::ENOSPC) && e.kind() != ErrorKind::Interrupted;
             break;
         }
+
+        state.last_completed = Some(n);
+        dashboard::emit(Event::FileDone {
+            number: n,
+            stats: None,
+        });
+        state.flow = flow.state();
+        state.elapsed_ms = elapsed_ms();
+        if let Err(e) = state.save(path) {
+            eprintln!("Error: Failed to write {}: {}", WRITE_STATE_NAME, e);
+        }
+
+        if state.spot_check > 0 {
+            let written: Vec<i64> = (state.start_at..=n).collect();
+            let spacing = if state.quick {
+                MARKER_SPACING
+            } else {
+                sector_size as u64
+            };
+            match spot_check(
+                path,
+                &written,
+                state.spot_check,
+                spacing,
+                &pattern,
+                &mut rng,
+            ) {
+                // Only earlier data overwritten by later files is a fake
+                // capacity, other damage is left to the verification.
+                Ok(stats) if stats.secs_overwritten() > 0 => {
+                    println!(
+                        "Spot check: {} of {} sampled sectors overwritten",
+                        stats.secs_overwritten(),
+                        state.spot_check
+                    );
+                    fake_capacity_at = Some(flow.total_processed());
+                    break;
+                }
+                Ok(stats) if stats.secs_lost() > 0 => {
+                    println!(
+                        "Spot check: {} of {} sampled sectors not ok, continuing",
+                        stats.secs_lost(),
+                        state.spot_check
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Error: Spot check failed: {}", e),
+            }
+        }
+    }
+
+    let was_interrupted = interrupted();
+    if was_interrupted {
+        // The manifest still lists the planned files and the state file
+        // points at the partial one, both are needed by --resume.
+        state.elapsed_ms = elapsed_ms();
+        if let Err(e) = state.save(path) {
+            eprintln!("Error: Failed to write {}: {}", WRITE_STATE_NAME, e);
+        }
+    } else {
+        manifest.update_sizes(path);
+        manifest.write_speeds = flow.speed_samples().to_vec();
+        if let Err(e) = manifest.save(path) {
+            eprintln!("Error: Failed to write {}: {}", MANIFEST_NAME, e);
+        }
+        // After a write error the state file is kept to retry with --resume
+        if !failed && let Err(e) = WriteState::remove(path) {
+            eprintln!("Error: Failed to delete {}: {}", WRITE_STATE_NAME, e);
+        }
     }
 
+    let summary = WriteSummary {
+        start_at: state.start_at,
+        end_at: state.end_at,
+        last_completed: state.last_completed,
+        bytes_written: flow.total_processed(),
+        avg_speed: flow.avg_speed_or(elapsed_ms()),
+        elapsed_ms: elapsed_ms(),
+        fake_capacity_at,
+        write_error: failed,
+        quick: state.quick,
+        sector_size,
+        speeds: flow.speed_samples().to_vec(),
+    };
+
     // Final report
     println!("--------------------REPORT--------------------");
+    if was_interrupted {
+        println!("WARNING: Interrupted, the results are partial");
+        match summary.last_completed {
+            Some(n) => println!("Last complete file: {}.h2w", n),
+            None => println!("No file was completed"),
+        }
+        let (size, unit) = adjust_unit(summary.bytes_written as f64);
+        println!("Written: {:.2} {}", size, unit);
+        println!("Run nf3_write --resume {} to continue", path);
+    } else if failed {
+        println!("WARNING: Stopped by a write error, the results are partial");
+        println!("Run nf3_write --resume {} to retry", path);
+    }
+    if let Some(bytes) = summary.fake_capacity_at {
+        println!(
+            "FAKE CAPACITY DETECTED at ~{} GiB: data written earlier was lost",
+            bytes.div_ceil(GIB)
+        );
+    }
     print_freespace(path);
-    if flow.has_enough_measurements() {
-        flow.pr_avg_speed();
+    if summary.avg_speed > 0.0 {
+        let (speed, unit) = adjust_unit(summary.avg_speed);
+        println!("Average speed: {:.2} {}/s", speed, unit);
     } else {
-        let total_time_ms = start_time.elapsed().as_millis() as i64;
-        if total_time_ms > 0 {
-            flow.pr_avg_speed();
-        } else {
-            println!("Writing speed not available");
-        }
+        println!("Writing speed not available");
     }
 
     println!(
         "Total elapsed: {:.2?}",
-        pr_time_str(start_time.elapsed().as_secs_f64())
+        pr_time_str(summary.elapsed_ms as f64 / 1000.0)
     );
 
-    Ok(())
+    Ok(summary)
+}
+
+/// True if `<path>/<number>.h2w` has `size` bytes and every sector (only
+/// the markers when `quick`) reads back ok.
+pub fn verify_written_file(
+    path: &str,
+    number: i64,
+    size: u64,
+    pattern: &Pattern,
+    quick: bool,
+) -> bool {
+    let full = format!("{}{}.h2w", path, number);
+    match std::fs::metadata(&full) {
+        Ok(meta) if meta.len() == size => {}
+        _ => return false,
+    }
+
+    if quick {
+        return check_markers(path, number, pattern)
+            .is_ok_and(|stats| stats.secs_ok() == size.div_ceil(MARKER_SPACING));
+    }
+
+    let mut stats = FileStats::new();
+    let mut flow = Flow::new(size, 0, false).with_block_size(pattern.sector_size);
+    stats
+        .validate_file(path, number as i32, pattern, &mut flow)
+        .is_ok()
+        && stats.secs_ok() * pattern.sector_size as u64 == size
+}
+
+/// Make sure `<path>/<number>.h2w` of `files` are on the device and evicted
+/// from the page cache, so that verifying them really reads the device.
+pub fn drop_file_caches(path: &str, files: &[i64]) {
+    for n in files {
+        let full = format!("{}{}.h2w", path, n);
+        let res = File::open(&full).and_then(|file| {
+            file.sync_data()?;
+            fadvise_dontneed(&file)
+        });
+        if let Err(e) = res {
+            eprintln!("Warning: Failed to drop caches of {}: {}", full, e);
+        }
+    }
+}
+
+/// Verify the files of the run `state` with the nf3_read engine, right
+/// after `fill_fs`. The pattern is known, so nothing is detected.
+pub fn verify_run(path: &str, state: &WriteState, show_progress: bool) -> Result<ReadSummary> {
+    let files = ls_my_files(path, state.start_at, state.end_at);
+    drop_file_caches(path, &files);
+
+    let issues = match Manifest::load(path) {
+        Ok(Some(manifest)) => manifest.check(path, &files, state.start_at, state.end_at),
+        _ => Vec::new(),
+    };
+    let opts = ReadOptions {
+        dev_path: path.to_string(),
+        start_at: state.start_at,
+        end_at: state.end_at,
+        hint: PatternHint {
+            kind: Some(state.pattern.kind),
+            seed: state.pattern.seed,
+            order: Some(state.pattern.order),
+            sector_size: Some(state.pattern.sector_size),
+            tolerance: Some(state.pattern.tolerance),
+        },
+        max_read_rate: 0,
+        show_progress,
+        quick: state.quick,
+        sample: 0,
+        sample_seed: 0,
+        // Nothing to resume, the verification is redone with the run
+        state_file: None,
+    };
+
+    println!("--------------------VERIFY--------------------");
+    let mut read_state = ReadState::new(state.start_at, state.end_at);
+    iterate_files(&opts, files, &issues, &mut read_state)
+}
+
+/// Consolidated report of `nf3_write --verify`.
+pub fn print_verify_summary(write: &WriteSummary, read: &ReadSummary) {
+    let speed = |bps: f64| {
+        if bps > 0.0 {
+            let (size, unit) = adjust_unit(bps);
+            format!("{:.2} {}/s", size, unit)
+        } else {
+            "not available".to_string()
+        }
+    };
+    let (ok, ok_unit) = adjust_unit(read.scaled_bytes(read.totals.secs_ok()) as f64);
+    let (lost, lost_unit) = adjust_unit(read.scaled_bytes(read.totals.secs_lost()) as f64);
+
+    println!("--------------------SUMMARY-------------------");
+    if read.quick {
+        println!("Speeds are not measured in quick mode");
+    } else {
+        println!("Writing speed: {}", speed(write.avg_speed));
+        println!("Reading speed: {}", speed(read.avg_speed));
+    }
+    println!(
+        "Data OK: {:.2} {}, Data LOST: {:.2} {}",
+        ok, ok_unit, lost, lost_unit
+    );
+    if read.data_lost() {
+        println!("Verdict: FAILED, the drive lost data");
+    } else {
+        println!("Verdict: OK, all data read back as written");
+    }
 }
 
 #[cfg(test)]
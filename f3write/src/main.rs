@@ -1,5 +1,6 @@
 // --- std ---
 // use std::fs::File;
+use std::{io, process};
 
 // --- external crates ---
 use clap::Parser;
@@ -9,7 +10,18 @@ use clap::Parser;
 // --- internal modules ---
 use f3core::{
     cli::WriteArgs,
-    utils::{self},
+    dashboard,
+    device::DeviceInfo,
+    guard,
+    history::{self, HistoryEntry},
+    interrupt::{self, EXIT_INTERRUPTED},
+    lock::RunLock,
+    pattern::Pattern,
+    report::RunReport,
+    speed,
+    state::{WRITE_STATE_NAME, WriteState},
+    utils::{self, SECTOR_SIZE},
+    verify::TOLERANCE,
 };
 use f3write::*;
 
@@ -28,6 +40,7 @@ fn main() {
     // log::info!("Starting program");
 
     let mut args = WriteArgs::parse();
+    interrupt::install_handler();
 
     // Validate the arguments
     args.validate_args();
@@ -37,21 +50,182 @@ fn main() {
 
     utils::adjust_dev_path(&mut args.common.dev_path);
 
-    utils::unlink_old_files(
-        &args.common.dev_path,
-        args.common.start_at,
-        args.common.end_at,
-    );
-    println!("Old files unlinked successfully.");
+    let device = DeviceInfo::probe(&args.common.dev_path);
+    let hazards = guard::check(&args.common.dev_path, device.as_ref());
+    if !hazards.is_empty() {
+        if let Some(dev) = &device {
+            println!(
+                "Target: {} on {} ({}, {})",
+                args.common.dev_path,
+                dev.mount_point,
+                dev.fs_type,
+                dev.describe()
+            );
+        }
+        for hazard in &hazards {
+            println!("\t{}", hazard);
+        }
+        if args.dry_run {
+            println!("A real run would be refused without --force");
+        } else if args.force {
+            println!("Writing anyway (--force)");
+        } else {
+            eprintln!(
+                "Error: Refusing to write to {}, pass --force if this is really the drive to test",
+                args.common.dev_path
+            );
+            process::exit(1);
+        }
+    }
+
+    if args.dry_run {
+        match plan_write(
+            &args.common.dev_path,
+            args.common.start_at,
+            args.common.end_at,
+        ) {
+            Ok(plan) => print_plan(&plan),
+            Err(e) => {
+                eprintln!("Error: Failed to plan the run: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let _lock = match RunLock::acquire(&args.common.dev_path, "nf3_write", args.common.wait_lock) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if e.kind() == io::ErrorKind::WouldBlock {
+                eprintln!("Pass --wait-lock to wait for it to finish");
+            }
+            process::exit(1);
+        }
+    };
 
+    if args.probe_cache > 0
+        && let Err(e) = probe_cache(&args.common.dev_path, args.probe_cache)
+    {
+        eprintln!("Error probing cache: {}", e);
+    }
+
+    let mut state = if args.resume {
+        match WriteState::load(&args.common.dev_path) {
+            Ok(Some(state)) => {
+                let (start_at, end_at) = (args.common.start_at, args.common.end_at);
+                if (start_at, end_at) != (1, 0)
+                    && (start_at, end_at) != (state.start_at, state.end_at)
+                {
+                    eprintln!(
+                        "Warning: --start-at/--end-at are ignored with --resume, continuing files {} to {}",
+                        state.start_at, state.end_at
+                    );
+                }
+                state
+            }
+            Ok(None) => {
+                eprintln!("Error: Nothing to resume, {} not found", WRITE_STATE_NAME);
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: Failed to load {}: {}", WRITE_STATE_NAME, e);
+                process::exit(1);
+            }
+        }
+    } else {
+        utils::unlink_old_files(
+            &args.common.dev_path,
+            args.common.start_at,
+            args.common.end_at,
+        );
+        println!("Old files unlinked successfully.");
+
+        let mut state = WriteState::new(
+            args.common.start_at,
+            args.common.end_at,
+            Pattern::new(args.common.pattern.unwrap_or_default(), args.common.seed)
+                .with_sector_size(args.common.sector_size.unwrap_or(SECTOR_SIZE))
+                .with_tolerance(args.common.tolerance.unwrap_or(TOLERANCE)),
+            args.max_write_rate,
+        );
+        state.spot_check = args.spot_check;
+        state.quick = args.quick;
+        state
+    };
+
+    if args.common.tui
+        && let Err(e) = dashboard::start()
+    {
+        eprintln!("Warning: Cannot show the dashboard: {}", e);
+    }
+    let mut report = RunReport::new(&args.common.dev_path);
+    report.describe_target(device);
     match fill_fs(
         &args.common.dev_path,
-        args.common.start_at,
-        &mut args.common.end_at,
-        args.max_write_rate,
+        &mut state,
+        args.resume,
         args.common.show_progress,
     ) {
-        Ok(()) => (),
+        Ok(summary) => report.write = Some(summary),
         Err(e) => eprintln!("Error in main: {}", e),
     }
+    report.run_id = Some(state.run_id.clone());
+
+    let fake = report
+        .write
+        .as_ref()
+        .is_some_and(|w| w.fake_capacity_at.is_some());
+    if args.verify && fake {
+        println!("Skipping verification, the drive already failed a spot check");
+    } else if args.verify
+        && !interrupt::interrupted()
+        && let Some(write) = &report.write
+    {
+        match verify_run(&args.common.dev_path, &state, args.common.show_progress) {
+            Ok(read) => {
+                if !interrupt::interrupted() {
+                    print_verify_summary(write, &read);
+                }
+                report.read = Some(read);
+            }
+            Err(e) => eprintln!("Error verifying files: {}", e),
+        }
+    }
+    report.interrupted = interrupt::interrupted();
+    dashboard::stop();
+
+    if let Some(prefix) = &args.common.speed_chart {
+        let write = report.write.as_ref().map_or(&[][..], |w| &w.speeds[..]);
+        let read = report.read.as_ref().map_or(&[][..], |r| &r.speeds[..]);
+        if let Err(e) = speed::export(prefix, write, read) {
+            eprintln!("Error: Failed to write speed chart {}: {}", prefix, e);
+        }
+    }
+    if let Some(file) = &args.common.report
+        && let Err(e) = report.save(file)
+    {
+        eprintln!("Error: Failed to write report {}: {}", file, e);
+    }
+    if let Some(file) = &args.common.html
+        && let Err(e) = report.save_html(file)
+    {
+        eprintln!("Error: Failed to write HTML report {}: {}", file, e);
+    }
+    if let Some(file) = args.common.history_file()
+        && let Err(e) = history::append(&file, &HistoryEntry::new("write", &report))
+    {
+        eprintln!(
+            "Error: Failed to record the run in {}: {}",
+            file.display(),
+            e
+        );
+    }
+    if report.interrupted {
+        process::exit(EXIT_INTERRUPTED);
+    }
+    let write_error = report.write.as_ref().is_some_and(|w| w.write_error);
+    if fake || write_error {
+        process::exit(1);
+    }
 }
@@ -2,7 +2,13 @@
 
 use crate::*;
 
-use f3core::flow::Flow;
+use f3core::{
+    file_fill::MARKER_SPACING,
+    flow::Flow,
+    pattern::{GeneratorKind, Pattern},
+    state::WriteState,
+    utils::SECTOR_SIZE,
+};
 
 #[test]
 fn integration_create_one_sector() {
@@ -10,7 +16,7 @@ fn integration_create_one_sector() {
     let p = dir.path().to_str().unwrap();
 
     let mut flow = Flow::new(512, -1, false);
-    let stop = create_and_fill_file(p, 1, 512, false, &mut flow);
+    let stop = create_and_fill_file(p, 1, 512, &Pattern::default(), false, false, &mut flow);
     assert!(stop.is_ok(), "Failed to create and fill file");
 
     let meta = std::fs::metadata(format!("{}/1.h2w", p)).unwrap();
@@ -18,3 +24,139 @@ fn integration_create_one_sector() {
 
     std::fs::remove_file(format!("{}/1.h2w", p)).unwrap();
 }
+
+#[test]
+fn verify_written_file_detects_partial_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    let size = 8 * SECTOR_SIZE as u64;
+    let pattern = Pattern::default();
+
+    let mut flow = Flow::new(size, -1, false);
+    create_and_fill_file(&p, 3, size, &pattern, false, false, &mut flow).unwrap();
+    assert!(verify_written_file(&p, 3, size, &pattern, false));
+
+    // Interrupted in the middle of the file
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(format!("{}3.h2w", p))
+        .unwrap();
+    file.set_len(size / 2).unwrap();
+    assert!(!verify_written_file(&p, 3, size, &pattern, false));
+    assert!(!verify_written_file(&p, 4, size, &pattern, false));
+}
+
+#[test]
+fn verify_run_reports_lost_data() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    let size = 8 * SECTOR_SIZE as u64;
+    let pattern = Pattern::new(GeneratorKind::Chacha, 42);
+
+    for n in 1..=2 {
+        let mut flow = Flow::new(size, -1, false);
+        create_and_fill_file(&p, n, size, &pattern, false, false, &mut flow).unwrap();
+    }
+    let mut state = WriteState::new(1, 3, pattern, 0);
+    state.last_completed = Some(2);
+
+    let read = verify_run(&p, &state, false).unwrap();
+    assert_eq!(read.totals.secs_ok(), 16);
+    assert!(!read.data_lost());
+
+    // Second file wiped out by the drive
+    std::fs::write(format!("{}2.h2w", p), vec![0u8; size as usize]).unwrap();
+    let read = verify_run(&p, &state, false).unwrap();
+    assert_eq!(read.totals.secs_ok(), 8);
+    assert!(read.data_lost());
+}
+
+#[test]
+fn quick_file_has_full_size_and_markers() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    let size = 4 * MARKER_SPACING;
+    let pattern = Pattern::default();
+
+    let mut flow = Flow::new(size, -1, false);
+    create_and_fill_file(&p, 2, size, &pattern, true, false, &mut flow).unwrap();
+    assert_eq!(
+        std::fs::metadata(format!("{}2.h2w", p)).unwrap().len(),
+        size
+    );
+    assert!(verify_written_file(&p, 2, size, &pattern, true));
+
+    let stats = f3core::verify::check_markers(&p, 2, &pattern).unwrap();
+    assert_eq!(stats.secs_ok(), 4);
+    assert_eq!(stats.secs_lost(), 0);
+
+    // A marker that doesn't read back
+    let mut data = std::fs::read(format!("{}2.h2w", p)).unwrap();
+    data[MARKER_SPACING as usize..MARKER_SPACING as usize + SECTOR_SIZE].fill(0xff);
+    std::fs::write(format!("{}2.h2w", p), data).unwrap();
+    assert!(!verify_written_file(&p, 2, size, &pattern, true));
+}
+
+#[test]
+fn plan_range_limits_files_to_free_space() {
+    const G: u64 = 1 << 30;
+    // Everything fits
+    assert_eq!(plan_range(10 * G, 3, 5), (3 * G, 5));
+    // No end: fill the free space, the last file partial
+    assert_eq!(plan_range(2 * G + 512, 1, 0), (2 * G + 512, 3));
+    // Not enough room for the requested files
+    assert_eq!(plan_range(G + 512, 1, 8), (G + 512, 2));
+}
+
+#[test]
+fn plan_write_lists_old_files_without_touching_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    std::fs::write(format!("{}2.h2w", p), vec![0u8; 100]).unwrap();
+    std::fs::write(format!("{}9.h2w", p), b"").unwrap();
+
+    let plan = plan_write(&p, 1, 2).unwrap();
+    assert_eq!(plan.delete, [(2, 100)]);
+    assert_eq!(plan.bytes, plan.create.iter().map(|(_, s)| s).sum::<u64>());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn plan_files_splits_the_bytes_into_gib_files() {
+    const G: u64 = 1 << 30;
+    assert_eq!(plan_files(10 * G, 1, 2), (2 * G, vec![(1, G), (2, G)]));
+    assert_eq!(
+        plan_files(2 * G + 512, 4, 0),
+        (2 * G + 512, vec![(4, G), (5, G), (6, 512)])
+    );
+    assert_eq!(plan_files(2 * G, 1, 0), (2 * G, vec![(1, G), (2, G)]));
+    assert_eq!(plan_files(0, 1, 0), (0, vec![]));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn full_drive_ends_the_run_normally() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    // Every write to /dev/full fails with ENOSPC, like the last file of a run
+    std::os::unix::fs::symlink("/dev/full", format!("{}1.h2w", p)).unwrap();
+
+    let mut state = WriteState::new(1, 2, Pattern::default(), 0);
+    let summary = fill_fs(&p, &mut state, false, false).unwrap();
+    assert!(!summary.write_error);
+    assert_eq!(summary.last_completed, None);
+    assert!(WriteState::load(&p).unwrap().is_none());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn write_error_keeps_the_state_for_resume() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = format!("{}/", dir.path().to_str().unwrap());
+    std::fs::create_dir(format!("{}1.h2w", p)).unwrap();
+
+    let mut state = WriteState::new(1, 2, Pattern::default(), 0);
+    let summary = fill_fs(&p, &mut state, false, false).unwrap();
+    assert!(summary.write_error);
+    assert!(WriteState::load(&p).unwrap().is_some());
+}
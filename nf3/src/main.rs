@@ -0,0 +1,94 @@
+// --- std ---
+use std::{path::PathBuf, process};
+
+// --- external crates ---
+use clap::{Parser, Subcommand};
+
+// --- internal modules ---
+use f3core::{
+    history::{self, HistoryEntry},
+    utils::adjust_unit,
+};
+
+/// Tools around the runs of nf3_write and nf3_read.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the recorded runs per device, with speed and data-loss trends
+    History {
+        /// History file (default: ~/.local/share/nf3/history.jsonl)
+        #[arg(long = "file", value_name = "FILE")]
+        file: Option<PathBuf>,
+        /// Only show devices containing TEXT
+        #[arg(long = "device", value_name = "TEXT")]
+        device: Option<String>,
+    },
+}
+
+fn main() {
+    let args = Args::parse();
+    match args.command {
+        Command::History { file, device } => {
+            let file = file.unwrap_or_else(history::default_path);
+            let entries = match history::load(&file) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    eprintln!("Error: Failed to read {}: {}", file.display(), e);
+                    process::exit(1);
+                }
+            };
+            print_history(&entries, device.as_deref());
+        }
+    }
+}
+
+fn print_history(entries: &[HistoryEntry], filter: Option<&str>) {
+    let devices = history::by_device(entries);
+    let mut shown = 0;
+    for (device, runs) in devices {
+        if filter.is_some_and(|f| !device.contains(f)) {
+            continue;
+        }
+        shown += 1;
+        println!("Device {} ({} runs)", device, runs.len());
+        println!(
+            "  {:<16}  {:<5}  {:>13}  {:>13}  {:>11}  VERDICT",
+            "STARTED (UTC)", "TOOL", "WRITE", "READ", "LOST"
+        );
+        for run in &runs {
+            let speed = |bps: Option<f64>| {
+                bps.map_or("-".to_string(), |b| {
+                    let (s, unit) = adjust_unit(b);
+                    format!("{:.2} {}/s", s, unit)
+                })
+            };
+            let lost = run.bytes_lost.map_or("-".to_string(), |b| {
+                let (s, unit) = adjust_unit(b as f64);
+                format!("{:.2} {}", s, unit)
+            });
+            println!(
+                "  {:<16}  {:<5}  {:>13}  {:>13}  {:>11}  {}",
+                history::utc_time(run.started_at),
+                run.tool,
+                speed(run.write_speed),
+                speed(run.read_speed),
+                lost,
+                run.verdict
+            );
+        }
+        let trend = history::trend(&runs);
+        if !trend.is_empty() {
+            println!("  Trend: {}", trend.join(", "));
+        }
+        println!();
+    }
+    if shown == 0 {
+        println!("No runs recorded");
+    }
+}